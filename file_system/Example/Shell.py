@@ -103,6 +103,9 @@ class Shell:
         self.fs.change_dir(args[0])
 
     def change_permissions(self, args: list[str]):
+        if len(args) == 3 and args[0] == "-R":
+            self.fs.change_permissions(args[1], args[2], recursive=True)
+            return
         if len(args) != 2:
             raise ShellError("Invalid number of arguments for chmod")
         self.fs.change_permissions(args[0], args[1])
@@ -25,6 +25,22 @@ class DirBlock:
     # Update with actual fields based on your Rust structure
     entries: List[DirEntry]
 
+@dataclass
+class EntryInfo:
+    name: str
+    file_type: FileType
+    size: int
+    blocks: int
+    link_count: int
+    blk_num: int
+    mode: int
+    uid: int
+    gid: int
+    created: int
+    modified: int
+    accessed: int
+    target: Optional[str]
+
 @dataclass
 class FileSystem:
     """
@@ -108,6 +124,19 @@ class FileSystem:
         """
         ...
 
+    def update_dir_sizes(self, path: str, delta: int) -> None:
+        """
+        Adds a signed size delta to the directory at the specified path and all of its ancestors.
+        """
+        ...
+
+    def recompute_dir_sizes(self) -> int:
+        """
+        Rebuilds the size of every directory from its contents and returns the total size.
+        A file with several names counts once per name.
+        """
+        ...
+
     def traverse_dir(self, path: str) -> DirBlock:
         """
         Traverses the directory structure and returns the directory block for the specified path.
@@ -126,15 +155,22 @@ class FileSystem:
         """
         ...
 
+    def cwd(self) -> str:
+        """
+        Returns the absolute path of the current working directory.
+        """
+        ...
+
     def print_working_dir(self) -> None:
         """
         Prints the current working directory.
         """
         ...
 
-    def format(self) -> None:
+    def format(self, dir_index: Optional[str] = None) -> None:
         """
         Formats the filesystem, erasing all data and resetting the system.
+        `dir_index` selects the directory layout, either "linear" (default) or "hashed".
         """
         ...
 
@@ -150,6 +186,18 @@ class FileSystem:
         """
         ...
 
+    def create_file_with_bytes(self, path: str, content: bytes) -> None:
+        """
+        Creates a new file holding the given bytes at the specified path.
+        """
+        ...
+
+    def create_file_from_base64(self, path: str, encoded: str) -> None:
+        """
+        Creates a new file holding the bytes the given base64 text decodes to.
+        """
+        ...
+
     def create_dir(self, path: str) -> None:
         """
         Creates a new directory at the specified path.
@@ -168,9 +216,52 @@ class FileSystem:
         """
         ...
 
+    def read(self, path: str) -> bytes:
+        """
+        Returns the content of the file at the specified path.
+        """
+        ...
+
+    def hexdump(self, path: str) -> None:
+        """
+        Prints the content of a file like hexdump -C.
+        """
+        ...
+
+    def xxd(self, path: str) -> None:
+        """
+        Prints the content of a file like xxd.
+        """
+        ...
+
     def append_file(self, source: str, dest: str) -> None:
         """
-        Appends the content of the source file to the destination file.
+        Appends the content of the source file to the destination file, nothing is put between them.
+        """
+        ...
+
+    def append_bytes(self, path: str, data: bytes) -> None:
+        """
+        Appends the given bytes to the file at the specified path.
+        """
+        ...
+
+    def write_at(self, path: str, offset: int, data: bytes) -> None:
+        """
+        Writes the given bytes into the file at the specified path at offset.
+        Writing past the end leaves a hole that reads as zeros and takes no blocks.
+        """
+        ...
+
+    def read_range(self, path: str, offset: int, len: int) -> bytes:
+        """
+        Reads up to len bytes of the file at the specified path from offset, fewer at the end of the file.
+        """
+        ...
+
+    def truncate(self, path: str, len: int) -> None:
+        """
+        Shortens or zero-extends the file at the specified path to len bytes.
         """
         ...
 
@@ -180,21 +271,172 @@ class FileSystem:
         """
         ...
 
-    def change_permissions(self, path: str, access_level: str) -> None:
+    def read_dir(self, path: str = ".") -> List[EntryInfo]:
+        """
+        Returns the entries of the directory at the specified path in listing order.
+        """
+        ...
+
+    def walk(self, path: str = ".", min_depth: int = 0, max_depth: Optional[int] = None, contents_first: bool = False) -> List[str]:
+        """
+        Returns the absolute paths of the entry at the specified path and everything below it,
+        directories before their contents unless contents_first is set. Symbolic links are not followed.
+        """
+        ...
+
+    def change_permissions(self, path: str, access_level: str, recursive: bool = False) -> None:
+        """
+        Changes the permissions of the file or directory at the specified path,
+        given as three octal digits like "644" or symbolic clauses like "u+x,g-w".
+        With recursive set, everything below a directory is changed as well.
+        """
+        ...
+
+    def change_owner(self, path: str, uid: Optional[int] = None, gid: Optional[int] = None) -> None:
+        """
+        Changes the owner and/or group of the file or directory at the specified path.
+        """
+        ...
+
+    def get_acl(self, path: str) -> List[str]:
+        """
+        Returns the access control list of the file or directory at the specified path,
+        one entry per string like "user:1000:rwx" or "default:deny:group:100:-w-".
+        """
+        ...
+
+    def modify_acl(self, path: str, specs: str) -> None:
+        """
+        Adds or changes comma separated ACL entries like "u:1000:rwx,d:g:100:r-x".
+        """
+        ...
+
+    def remove_acl_entries(self, path: str, specs: str) -> None:
+        """
+        Removes the comma separated ACL entries like "u:1000,d:g:100".
+        """
+        ...
+
+    def clear_acl(self, path: str) -> None:
+        """
+        Removes the whole access control list of the file or directory at the specified path.
+        """
+        ...
+
+    def get_xattr(self, path: str, name: str) -> bytes:
+        """
+        Returns the value of the extended attribute with the given name, like "user.author".
+        """
+        ...
+
+    def set_xattr(self, path: str, name: str, value: bytes) -> None:
+        """
+        Sets an extended attribute of the file or directory at the specified path,
+        names are up to 255 bytes and values up to 64 KiB.
+        """
+        ...
+
+    def list_xattr(self, path: str) -> List[str]:
+        """
+        Lists the names of the extended attributes of the file or directory at the specified path.
+        """
+        ...
+
+    def remove_xattr(self, path: str, name: str) -> None:
+        """
+        Removes an extended attribute of the file or directory at the specified path.
+        """
+        ...
+
+    def set_credentials(self, uid: int, gid: int) -> None:
+        """
+        Sets the user and group that every following operation acts as.
+        """
+        ...
+
+    def add_user(self, name: str) -> int:
+        """
+        Adds a locked account with a home directory and returns its uid, only root may add accounts.
+        """
+        ...
+
+    def set_password(self, name: str, password: str) -> None:
+        """
+        Sets the password of an account, root may do so for everyone and other users only for themselves.
+        """
+        ...
+
+    def login(self, name: str, password: str) -> int:
+        """
+        Checks the password, acts as the user from now on and changes to its home directory. Returns its uid.
+        """
+        ...
+
+    def switch_user(self, name: str, password: Optional[str] = None) -> int:
         """
-        Changes the permissions of the file or directory at the specified path.
+        Acts as another user from now on, the password is only needed if the caller isn't root. Returns its uid.
         """
         ...
 
     def copy_entry(self, source: str, dest: str) -> None:
         """
-        Copies a file or directory from the source to the destination path.
+        Copies a file or directory with everything below it from the source to the destination path,
+        nothing is copied unless it all fits on the volume.
+        """
+        ...
+
+    def move_entry(self, source: str, dest: str, overwrite: bool = False) -> None:
+        """
+        Moves a file or directory from the source to the destination path,
+        replacing an existing destination if overwrite is set.
+        """
+        ...
+
+    def df(self) -> None:
+        """
+        Prints the total, used and free space of the filesystem.
+        """
+        ...
+
+    def du(self, path: str, summarize: bool = False) -> None:
+        """
+        Prints the recursive space usage of the file or directory at the specified path.
+        """
+        ...
+
+    def create_symlink(self, target: str, path: str) -> None:
+        """
+        Creates a symbolic link at the specified path pointing to the target path.
+        """
+        ...
+
+    def create_hard_link(self, target: str, path: str) -> None:
+        """
+        Creates a hard link at the specified path sharing the data of the target file.
+        """
+        ...
+
+    def read_link(self, path: str) -> str:
+        """
+        Returns the target of the symbolic link at the specified path.
+        """
+        ...
+
+    def stat(self, path: str) -> None:
+        """
+        Prints the metadata of the entry at the specified path, including its times.
+        """
+        ...
+
+    def metadata(self, path: str) -> EntryInfo:
+        """
+        Returns the metadata of the entry at the specified path, symbolic links are not followed.
         """
         ...
 
-    def move_entry(self, source: str, dest: str) -> None:
+    def set_atime_policy(self, policy: str) -> None:
         """
-        Moves a file or directory from the source to the destination path.
+        Sets when reading a file updates its access time: "strict", "relatime" or "noatime".
         """
         ...
 
@@ -214,6 +456,7 @@ class Disk:
 class FileType:
     File = ...
     Directory = ...
+    Symlink = ...
 
     def __init__(self) -> None: ...
     # If there are any methods related to FileType, add them here
@@ -1,6 +1,7 @@
 use pyo3_build_config::use_pyo3_cfgs;
 
 fn main() {
+    println!("cargo::rustc-check-cfg=cfg(PyPy)");
     // use pyo3-buildconig to expose cfg flags to the build script
     use_pyo3_cfgs();
 }
\ No newline at end of file
@@ -0,0 +1,314 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use logger_macro::trace_log;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::credentials::Credentials;
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::FileError;
+use crate::traits::AclHandling;
+use crate::utils::side_blocks::NO_SIDE_BLOCK;
+use crate::FileSystem;
+
+/// `acl_blk` of an entry without an access control list.
+pub(crate) const NO_ACL: u16 = NO_SIDE_BLOCK;
+
+/// Who an ACL entry applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AclTag {
+    User(u16),
+    Group(u16),
+}
+
+/// Whether an ACL entry grants or denies its bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AclKind {
+    Allow,
+    Deny,
+}
+
+/// One rule of an access control list, written as `[default:][deny:]user|group:id:rwx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AclEntry {
+    /// Default entries only live on directories and are handed down to the entries created in them.
+    pub default: bool,
+    pub kind: AclKind,
+    pub tag: AclTag,
+    /// Read, write and execute bits, like one class of a mode.
+    pub perms: u8,
+}
+
+impl AclEntry {
+    /// Returns `true` if both entries are the same rule, no matter which bits they hold.
+    fn same_rule(&self, other: &AclEntry) -> bool {
+        self.default == other.default && self.kind == other.kind && self.tag == other.tag
+    }
+}
+
+/// Formats 3 permission bits as `rwx`.
+fn format_perms(perms: u8) -> String {
+    [(4, 'r'), (2, 'w'), (1, 'x')]
+        .iter()
+        .map(|&(bit, symbol)| if perms & bit != 0 { symbol } else { '-' })
+        .collect()
+}
+
+impl fmt::Display for AclEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.default {
+            write!(f, "default:")?;
+        }
+        if self.kind == AclKind::Deny {
+            write!(f, "deny:")?;
+        }
+        match self.tag {
+            AclTag::User(uid) => write!(f, "user:{}:", uid)?,
+            AclTag::Group(gid) => write!(f, "group:{}:", gid)?,
+        }
+        write!(f, "{}", format_perms(self.perms))
+    }
+}
+
+impl FromStr for AclEntry {
+    type Err = FileError;
+
+    /// Parses `[d[efault]:][deny:]u[ser]|g[roup]:id[:perms]`, the perms like `rwx`, `r-x` or `rw`.
+    ///
+    /// The perms may be left out to name a rule that is to be removed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FileError::InvalidAcl(s.to_string());
+        let mut fields = s.split(':').peekable();
+
+        let default = fields.next_if(|&f| f == "d" || f == "default").is_some();
+        let kind = match fields.next_if(|&f| f == "deny" || f == "allow") {
+            Some("deny") => AclKind::Deny,
+            _ => AclKind::Allow,
+        };
+        let tag = fields.next().ok_or_else(invalid)?;
+        let id = fields
+            .next()
+            .and_then(|id| id.parse::<u16>().ok())
+            .ok_or_else(invalid)?;
+        let tag = match tag {
+            "u" | "user" => AclTag::User(id),
+            "g" | "group" => AclTag::Group(id),
+            _ => return Err(invalid()),
+        };
+
+        let mut perms = 0;
+        for symbol in fields.next().unwrap_or_default().chars() {
+            perms |= match symbol {
+                'r' => 4,
+                'w' => 2,
+                'x' => 1,
+                '-' => 0,
+                _ => return Err(invalid()),
+            };
+        }
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(AclEntry {
+            default,
+            kind,
+            tag,
+            perms,
+        })
+    }
+}
+
+/// The named users and groups an entry grants or denies access to beyond its mode.
+///
+/// Stored in a block of its own that the entry points to through `acl_blk`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Acl {
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds `entry`, replacing the bits of the same rule if there already is one.
+    pub fn set(&mut self, entry: AclEntry) {
+        match self.entries.iter_mut().find(|e| e.same_rule(&entry)) {
+            Some(existing) => existing.perms = entry.perms,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Removes the rule `entry` names, its bits are ignored.
+    pub fn remove(&mut self, entry: &AclEntry) {
+        self.entries.retain(|e| !e.same_rule(entry));
+    }
+
+    /// Returns the list a new entry of `file_type` gets when created in a directory with this list.
+    ///
+    /// The default entries become the access entries of the new entry,
+    /// a new directory also keeps them as its own defaults.
+    fn inherited(&self, file_type: FileType) -> Acl {
+        let defaults = self.entries.iter().filter(|e| e.default);
+        let mut entries = defaults
+            .clone()
+            .map(|e| AclEntry {
+                default: false,
+                ..*e
+            })
+            .collect::<Vec<_>>();
+        if file_type == FileType::Directory {
+            entries.extend(defaults.copied());
+        }
+        Acl { entries }
+    }
+
+    /// Returns the bits the access entries naming `tag` allow, `None` if none allows anything,
+    /// and the bits they deny.
+    fn rules_for(&self, tag: AclTag) -> (Option<u8>, u8) {
+        self.entries
+            .iter()
+            .filter(|e| !e.default && e.tag == tag)
+            .fold((None, 0), |(allow, deny), e| match e.kind {
+                AclKind::Allow => (Some(allow.unwrap_or(0) | e.perms), deny),
+                AclKind::Deny => (allow, deny | e.perms),
+            })
+    }
+
+    /// Returns the 3 bits `credentials` get on `entry` under this list.
+    ///
+    /// The owner always gets the owner bits. Otherwise the entries allowing the user decide,
+    /// then the group bits together with the entries allowing the group, then the other bits.
+    /// Every bit denied to the user or their group is taken away afterwards.
+    pub(crate) fn granted(&self, credentials: &Credentials, entry: &DirEntry) -> u8 {
+        let class = |shift: u16| ((entry.mode >> shift) & 0o7) as u8;
+
+        if credentials.uid == entry.uid {
+            return class(6);
+        }
+        let (user_allow, user_deny) = self.rules_for(AclTag::User(credentials.uid));
+        let (group_allow, group_deny) = self.rules_for(AclTag::Group(credentials.gid));
+
+        let allowed = match user_allow {
+            Some(allow) => allow,
+            None if credentials.gid == entry.gid => class(3) | group_allow.unwrap_or(0),
+            None => group_allow.unwrap_or(class(0)),
+        };
+        allowed & !(user_deny | group_deny)
+    }
+}
+
+impl fmt::Display for Acl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl FileSystem {
+    /// Reads the access control list of `entry`, `None` if it has none.
+    #[trace_log]
+    pub(crate) fn read_acl(&self, entry: &DirEntry) -> Result<Option<Acl>> {
+        self.read_side_block(entry.acl_blk)
+    }
+
+    /// Writes `acl` to a new block and returns it, an empty list needs no block.
+    #[trace_log]
+    fn write_acl(&mut self, acl: &Acl) -> Result<u16> {
+        if acl.is_empty() {
+            return Ok(NO_ACL);
+        }
+        self.write_side_block(acl)
+    }
+
+    /// Writes the list a new entry of `file_type` gets from the default entries of `parent`
+    /// and returns its block.
+    #[trace_log]
+    pub(crate) fn inherit_acl(&mut self, parent: &DirEntry, file_type: FileType) -> Result<u16> {
+        match self.read_acl(parent)? {
+            Some(acl) => self.write_acl(&acl.inherited(file_type)),
+            None => Ok(NO_ACL),
+        }
+    }
+}
+
+impl AclHandling for FileSystem {
+    /// Returns the access control list of the entry at `path`, empty if it has none.
+    #[trace_log]
+    fn get_acl(&self, path: &str) -> Result<Acl> {
+        let entry = self.lookup_entry(path, true)?;
+        Ok(self.read_acl(&entry)?.unwrap_or_default())
+    }
+
+    /// Replaces the access control list of the entry at `path`.
+    ///
+    /// Only the owner and the superuser may change it,
+    /// default entries can only be given to directories.
+    #[trace_log]
+    fn set_acl(&mut self, path: &str, acl: &Acl) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if abs_path == "/" {
+            return Err(FileError::NotPermitted(abs_path).into());
+        }
+        self.check_owner(&entry)?;
+        if entry.file_type != FileType::Directory && acl.entries().iter().any(|e| e.default) {
+            return Err(FileError::NotADirectory(entry.name).into());
+        }
+
+        let acl_blk = self.write_acl(acl)?;
+        self.replace_side_block(&abs_path, entry.acl_blk, acl_blk, |entry| {
+            entry.acl_blk = acl_blk
+        })
+    }
+
+    /// Adds or changes the comma separated entries of `specs`, like `u:1000:rwx,d:g:100:r-x`.
+    #[trace_log]
+    fn modify_acl(&mut self, path: &str, specs: &str) -> Result<()> {
+        let mut acl = self.get_acl(path)?;
+        for spec in specs.split(',') {
+            acl.set(spec.parse()?);
+        }
+        self.set_acl(path, &acl)
+    }
+
+    /// Removes the rules named by the comma separated entries of `specs`, like `u:1000`.
+    #[trace_log]
+    fn remove_acl_entries(&mut self, path: &str, specs: &str) -> Result<()> {
+        let mut acl = self.get_acl(path)?;
+        for spec in specs.split(',') {
+            acl.remove(&spec.parse()?);
+        }
+        self.set_acl(path, &acl)
+    }
+
+    /// Prints the owner, the group, the mode and the access control list of the entry at `path`.
+    #[trace_log]
+    fn getfacl(&mut self, path: &str) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        let acl = self.read_acl(&entry)?.unwrap_or_default();
+        let class = |shift: u16| format_perms(((entry.mode >> shift) & 0o7) as u8);
+
+        let output = format!(
+            "# file: {}\n# owner: {}\n# group: {}\nuser::{}\ngroup::{}\nother::{}\n{}",
+            abs_path,
+            entry.uid,
+            entry.gid,
+            class(6),
+            class(3),
+            class(0),
+            acl
+        );
+        self.io_handler.write(output)?;
+        Ok(())
+    }
+}
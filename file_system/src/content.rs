@@ -0,0 +1,394 @@
+use std::io::{self, Read};
+
+use anyhow::Result;
+use logger_macro::trace_log;
+use rustic_disk::traits::BlockStorage;
+use rustic_disk::Disk;
+
+use crate::dir_entry::DirEntry;
+use crate::errors::{FSError, FileError};
+use crate::fat::{FatType, FAT};
+use crate::{FileSystem, FAT_BLK, MAX_FILE_SIZE};
+
+/// The content of a regular file is stored block-aligned without any framing,
+/// byte `n` lives at offset `n % BLOCK_SIZE` of logical block `n / BLOCK_SIZE`
+/// and the `size` of the entry tells where it ends.
+///
+/// Logical blocks of zeros may be left out of the chain as holes, the FAT records how many
+/// follow each block. The first block is always allocated, it identifies the file.
+const BLOCK_SIZE: u64 = Disk::BLOCK_SIZE as u64;
+
+/// Reads from `reader` until `block` is full or the reader is exhausted
+/// and returns the number of bytes read.
+fn fill_block(reader: &mut impl Read, block: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// The allocated blocks of a chain in order, with their logical block index.
+struct ChainBlocks<'a> {
+    fat: &'a FAT,
+    next: Option<(u64, u16)>,
+}
+
+impl Iterator for ChainBlocks<'_> {
+    type Item = Result<(u64, u16)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, blk) = self.next.take()?;
+        self.next = match self.fat.get(blk as usize) {
+            Some(&FatType::Taken(next_blk)) => {
+                Some((index + self.fat.holes_after(blk) + 1, next_blk))
+            }
+            Some(&FatType::EOF) => None,
+            _ => return Some(Err(FSError::InvalidBlockReference.into())),
+        };
+        Some(Ok((index, blk)))
+    }
+}
+
+impl FileSystem {
+    fn chain_blocks(&self, start_blk: u16) -> ChainBlocks<'_> {
+        ChainBlocks {
+            fat: &self.fat,
+            next: Some((0, start_blk)),
+        }
+    }
+
+    /// Returns the last allocated block of the chain starting at `start_blk`
+    /// at or before the logical block `index`, together with its own index.
+    fn block_at_or_before(&self, start_blk: u16, index: u64) -> Result<(u64, u16)> {
+        let mut found = (0, start_blk);
+        for block in self.chain_blocks(start_blk) {
+            let (block_index, blk) = block?;
+            if block_index > index {
+                break;
+            }
+            found = (block_index, blk);
+        }
+        Ok(found)
+    }
+
+    /// Returns the block of the chain starting at `start_blk` that holds the byte at `offset`,
+    /// `None` if the byte lies in a hole.
+    #[trace_log]
+    pub(crate) fn content_block(&self, start_blk: u16, offset: u64) -> Result<Option<u16>> {
+        let index = offset / BLOCK_SIZE;
+        let (block_index, blk) = self.block_at_or_before(start_blk, index)?;
+        Ok((block_index == index).then_some(blk))
+    }
+
+    /// Writes `content` to a chain starting at `start_blk`, which the caller has already taken,
+    /// and links as many free blocks behind it as the content needs.
+    ///
+    /// # Errors
+    /// Returns `FSError::NoFreeBlocks` if the volume runs out of blocks.
+    #[trace_log]
+    pub(crate) fn write_content(&mut self, start_blk: u16, content: &[u8]) -> Result<()> {
+        self.write_content_from(start_blk, &mut &*content)?;
+        Ok(())
+    }
+
+    /// Streams everything `reader` yields into a chain starting at `start_blk`,
+    /// which the caller has already taken, one block at a time, and returns the number of bytes.
+    ///
+    /// Whole blocks of zeros after the first are left out as holes while the FAT has room for them.
+    /// The chain ends properly even if this fails, so the caller can free it.
+    ///
+    /// # Errors
+    /// Returns `FSError::NoFreeBlocks` if the volume runs out of blocks,
+    /// `FileError::FileTooLarge` if the reader yields more than `MAX_FILE_SIZE` bytes
+    /// and any error of the reader.
+    pub(crate) fn write_content_from(
+        &mut self,
+        start_blk: u16,
+        reader: &mut impl Read,
+    ) -> Result<u64> {
+        let mut blk = start_blk;
+        let mut block = vec![0u8; Disk::BLOCK_SIZE];
+        let mut size = 0;
+        let mut holes = 0;
+
+        let result = loop {
+            let n = match fill_block(reader, &mut block) {
+                Ok(0) => break Ok(size),
+                Ok(n) if size + n as u64 > MAX_FILE_SIZE => {
+                    break Err(FileError::FileTooLarge.into())
+                }
+                Ok(n) => n,
+                Err(e) => break Err(e.into()),
+            };
+            if size > 0 {
+                if n == Disk::BLOCK_SIZE
+                    && (holes > 0 || self.fat.can_add_hole())
+                    && block.iter().all(|&b| b == 0)
+                {
+                    holes += 1;
+                    size += BLOCK_SIZE;
+                    continue;
+                }
+                match self.get_free_block() {
+                    Ok(new_blk) => {
+                        self.set_fat_block(blk, FatType::Taken(new_blk))?;
+                        self.fat.set_holes_after(blk, holes);
+                        holes = 0;
+                        blk = new_blk;
+                    }
+                    Err(e) => break Err(e),
+                }
+            }
+            if let Err(e) = self.disk.write_raw_data(blk as usize, &block[..n]) {
+                break Err(e.into());
+            }
+            size += n as u64;
+
+            // a short block means the reader is exhausted
+            if n < Disk::BLOCK_SIZE {
+                break Ok(size);
+            }
+        };
+
+        self.set_fat_block(blk, FatType::EOF)?;
+        self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+        result
+    }
+
+    /// Writes `data` into the content of the file `entry` at `offset` in place,
+    /// only the blocks it covers are written. Blocks in holes are allocated as they are written to,
+    /// writing past the end leaves a hole between the old end and `offset`.
+    ///
+    /// The caller sets the new size. If this fails, the blocks linked past the old end are freed
+    /// and the file ends where it did, the part of `data` written before the old end stays.
+    ///
+    /// # Errors
+    /// Returns `FSError::NoFreeBlocks` if the volume runs out of blocks
+    /// and `FileError::FileTooLarge` if the file would grow past `MAX_FILE_SIZE`.
+    #[trace_log]
+    pub(crate) fn write_content_at(
+        &mut self,
+        entry: &DirEntry,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if offset
+            .checked_add(data.len() as u64)
+            .is_none_or(|end| end > MAX_FILE_SIZE)
+        {
+            return Err(FileError::FileTooLarge.into());
+        }
+        if offset > entry.size {
+            self.resize_content(entry, offset)?;
+        }
+
+        if let Err(e) = self.write_blocks_at(entry, offset, data) {
+            self.resize_content(entry, entry.size)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Writes `data` block by block from `offset`, linking blocks for holes and the new end.
+    fn write_blocks_at(&mut self, entry: &DirEntry, offset: u64, data: &[u8]) -> Result<()> {
+        let mut pos = offset;
+        let mut rest = data;
+        while !rest.is_empty() {
+            let index = pos / BLOCK_SIZE;
+            let start = (pos % BLOCK_SIZE) as usize;
+            let (chunk, tail) = rest.split_at((Disk::BLOCK_SIZE - start).min(rest.len()));
+
+            let (block_index, blk) = self.block_at_or_before(entry.blk_num, index)?;
+            let (blk, mut block) = if block_index == index {
+                (blk, self.disk.read_raw_data(blk as usize)?)
+            } else {
+                let new_blk = self.insert_block(blk, block_index, index)?;
+                (new_blk, vec![0u8; Disk::BLOCK_SIZE])
+            };
+            block[start..start + chunk.len()].copy_from_slice(chunk);
+            self.disk.write_raw_data(blk as usize, &block)?;
+
+            pos += chunk.len() as u64;
+            rest = tail;
+        }
+
+        self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+        Ok(())
+    }
+
+    /// Links a new block for the logical block `index` behind `prev_blk` at `prev_index`,
+    /// splitting the hole between them, and returns it.
+    ///
+    /// If the FAT has no hole record left for the split, the gap before the new block
+    /// is filled with blocks of zeros instead, like `write_content_from` does.
+    fn insert_block(&mut self, prev_blk: u16, prev_index: u64, index: u64) -> Result<u16> {
+        let next = self.fat[prev_blk as usize];
+        let mut holes = self.fat.holes_after(prev_blk);
+        let mut before = index - prev_index - 1;
+        let after = match next {
+            FatType::Taken(_) => prev_index + holes - index,
+            _ => 0,
+        };
+
+        let mut prev_blk = prev_blk;
+        let added = (before > 0) as usize + (after > 0) as usize;
+        if added > (holes > 0) as usize && !self.fat.can_add_hole() {
+            // the chain stays whole after every block, so a failure leaves nothing dangling
+            let zeros = vec![0u8; Disk::BLOCK_SIZE];
+            while before > 0 {
+                let zero_blk = self.get_free_block()?;
+                self.disk.write_raw_data(zero_blk as usize, &zeros)?;
+                holes = holes.saturating_sub(1);
+                self.link_block(prev_blk, zero_blk, next, holes)?;
+                prev_blk = zero_blk;
+                before -= 1;
+            }
+        }
+
+        let new_blk = self.get_free_block()?;
+        self.link_block(prev_blk, new_blk, next, after)?;
+        self.fat.set_holes_after(prev_blk, before);
+        Ok(new_blk)
+    }
+
+    /// Links `blk` behind `prev_blk`, followed by `holes` zero blocks and `next`.
+    fn link_block(&mut self, prev_blk: u16, blk: u16, next: FatType, holes: u64) -> Result<()> {
+        self.set_fat_block(prev_blk, FatType::Taken(blk))?;
+        self.fat.set_holes_after(prev_blk, 0);
+        self.set_fat_block(blk, next)?;
+        self.fat.set_holes_after(blk, holes);
+        Ok(())
+    }
+
+    /// Shortens or extends the content of the file `entry` to `size` bytes.
+    ///
+    /// Shrinking ends the chain at the last block before the new end and frees the blocks behind it.
+    /// Growing only zeroes the rest of the block holding the old end,
+    /// everything past it is a hole that takes no blocks.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileTooLarge` if `size` is larger than `MAX_FILE_SIZE`.
+    #[trace_log]
+    pub(crate) fn resize_content(&mut self, entry: &DirEntry, size: u64) -> Result<()> {
+        if size > MAX_FILE_SIZE {
+            return Err(FileError::FileTooLarge.into());
+        }
+        if size <= entry.size {
+            let (_, blk) =
+                self.block_at_or_before(entry.blk_num, size.saturating_sub(1) / BLOCK_SIZE)?;
+            if let Some(&FatType::Taken(next_blk)) = self.fat.get(blk as usize) {
+                self.clear_file_data(next_blk)?;
+            }
+            self.set_fat_block(blk, FatType::EOF)?;
+            self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+            return Ok(());
+        }
+
+        // bytes past the old end may hold leftovers of earlier content
+        if let Some(blk) = self.content_block(entry.blk_num, entry.size)? {
+            let mut block = self.disk.read_raw_data(blk as usize)?;
+            block[(entry.size % BLOCK_SIZE) as usize..].fill(0);
+            self.disk.write_raw_data(blk as usize, &block)?;
+        }
+        Ok(())
+    }
+
+    /// Reads `len` bytes of the content of the file `entry` from `offset`,
+    /// fewer if the file ends before that.
+    ///
+    /// Only the blocks holding the range are read, holes read as zeros
+    /// and the FAT links before the range are followed without touching their blocks.
+    #[trace_log]
+    pub(crate) fn read_content_range(
+        &self,
+        entry: &DirEntry,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        let end = offset.saturating_add(len).min(entry.size);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let mut content = vec![0u8; (end - offset) as usize];
+        for block in self.chain_blocks(entry.blk_num) {
+            let (index, blk) = block?;
+            let block_start = index * BLOCK_SIZE;
+            if block_start >= end {
+                break;
+            }
+            if block_start + BLOCK_SIZE <= offset {
+                continue;
+            }
+
+            let data = self.disk.read_raw_data(blk as usize)?;
+            let from = offset.max(block_start);
+            let to = end.min(block_start + BLOCK_SIZE);
+            content[(from - offset) as usize..(to - offset) as usize]
+                .copy_from_slice(&data[(from - block_start) as usize..(to - block_start) as usize]);
+        }
+
+        Ok(content)
+    }
+
+    /// Reads the whole content of the file `entry`.
+    #[trace_log]
+    pub(crate) fn read_content(&self, entry: &DirEntry) -> Result<Vec<u8>> {
+        self.read_content_range(entry, 0, entry.size)
+    }
+
+    /// Copies the content of the file `entry` to a chain starting at `start_blk`,
+    /// which the caller has already taken, one block at a time.
+    ///
+    /// The holes of the source stay holes while the FAT has room for them,
+    /// otherwise they are filled with blocks of zeros.
+    /// The chain ends properly even if this fails, so the caller can free it.
+    ///
+    /// # Errors
+    /// Returns `FSError::NoFreeBlocks` if the volume runs out of blocks.
+    #[trace_log]
+    pub(crate) fn copy_content(&mut self, entry: &DirEntry, start_blk: u16) -> Result<()> {
+        let result = self.copy_blocks(entry.blk_num, start_blk);
+        self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+        result
+    }
+
+    fn copy_blocks(&mut self, mut src_blk: u16, mut blk: u16) -> Result<()> {
+        let zeros = vec![0u8; Disk::BLOCK_SIZE];
+        loop {
+            let block = self.disk.read_raw_data(src_blk as usize)?;
+            self.disk.write_raw_data(blk as usize, &block)?;
+
+            let next_src = match self.fat.get(src_blk as usize) {
+                Some(&FatType::Taken(next_src)) => next_src,
+                Some(&FatType::EOF) => return Ok(()),
+                _ => return Err(FSError::InvalidBlockReference.into()),
+            };
+            let mut holes = self.fat.holes_after(src_blk);
+            if holes > 0 && !self.fat.can_add_hole() {
+                for _ in 0..holes {
+                    let zero_blk = self.get_free_block()?;
+                    self.disk.write_raw_data(zero_blk as usize, &zeros)?;
+                    self.link_block(blk, zero_blk, FatType::EOF, 0)?;
+                    blk = zero_blk;
+                }
+                holes = 0;
+            }
+
+            let next_blk = self.get_free_block()?;
+            self.link_block(blk, next_blk, FatType::EOF, 0)?;
+            self.fat.set_holes_after(blk, holes);
+            blk = next_blk;
+            src_blk = next_src;
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use logger_macro::trace_log;
+
+use crate::acl::NO_ACL;
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::FileError;
+use crate::{FileSystem, EXECUTE, READ, WRITE};
+
+/// User id of the superuser, who passes every permission check.
+pub const ROOT_UID: u16 = 0;
+/// Group id of the superuser.
+pub const ROOT_GID: u16 = 0;
+/// User id of nobody, who owns nothing and only gets the other bits.
+pub const NOBODY_UID: u16 = u16::MAX - 1;
+/// Group id of nobody.
+pub const NOBODY_GID: u16 = u16::MAX - 1;
+
+/// The user and group on whose behalf the file system is accessed.
+///
+/// New entries are owned by them and every permission check
+/// picks the owner, group or other bits of a mode based on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u16,
+    pub gid: u16,
+}
+
+impl Credentials {
+    pub fn new(uid: u16, gid: u16) -> Self {
+        Credentials { uid, gid }
+    }
+
+    /// The credentials of the superuser.
+    pub fn root() -> Self {
+        Credentials::new(ROOT_UID, ROOT_GID)
+    }
+
+    /// The credentials of a caller that hasn't logged in.
+    pub fn nobody() -> Self {
+        Credentials::new(NOBODY_UID, NOBODY_GID)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == ROOT_UID
+    }
+
+    /// Returns the 3-bit class of `mode` that applies to these credentials for an entry
+    /// owned by `uid` and `gid`, the owner bits win over the group bits.
+    pub(crate) fn class_bits(&self, mode: u16, uid: u16, gid: u16) -> u8 {
+        let shift = if self.uid == uid {
+            6
+        } else if self.gid == gid {
+            3
+        } else {
+            0
+        };
+        ((mode >> shift) & 0o7) as u8
+    }
+
+    /// Returns `true` if these credentials grant every bit of `required` on `entry`.
+    ///
+    /// The superuser may read and write anything and enter any directory,
+    /// but only executes files that have an execute bit in some class.
+    pub(crate) fn allows(&self, entry: &DirEntry, required: u8) -> bool {
+        if self.is_root() {
+            return required & EXECUTE == 0
+                || entry.file_type == FileType::Directory
+                || entry.mode & 0o111 != 0;
+        }
+
+        let granted = self.class_bits(entry.mode, entry.uid, entry.gid);
+        (granted & required) == required
+    }
+}
+
+impl Default for Credentials {
+    fn default() -> Self {
+        Credentials::root()
+    }
+}
+
+impl FileSystem {
+    pub fn credentials(&self) -> Credentials {
+        self.credentials
+    }
+
+    /// Sets the user and group every following operation acts as.
+    pub fn set_credentials(&mut self, credentials: Credentials) {
+        self.credentials = credentials;
+    }
+
+    /// Checks that the current credentials may change the mode or group of `entry`,
+    /// which only its owner and the superuser can.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotOwner` otherwise.
+    #[trace_log]
+    pub(crate) fn check_owner(&self, entry: &DirEntry) -> Result<()> {
+        if self.credentials.is_root() || self.credentials.uid == entry.uid {
+            Ok(())
+        } else {
+            Err(FileError::NotOwner(entry.name.to_string()).into())
+        }
+    }
+
+    /// Checks that the current credentials grant every bit of `required` on `entry`,
+    /// every operation goes through here before it reads, changes or enters an entry.
+    ///
+    /// The access control list of the entry is consulted if it has one.
+    ///
+    /// # Errors
+    /// Returns `FileError::NoPermissionToRead`, `NoPermissionToWrite` or `NoPermissionToExecute`
+    /// for the first missing bit in that order.
+    #[trace_log]
+    pub(crate) fn check_access(&self, entry: &DirEntry, required: u8) -> Result<()> {
+        let granted = if self.credentials.is_root() {
+            if self.credentials.allows(entry, required) {
+                return Ok(());
+            }
+            READ | WRITE
+        } else if entry.acl_blk == NO_ACL {
+            self.credentials.class_bits(entry.mode, entry.uid, entry.gid)
+        } else {
+            match self.read_acl(entry)? {
+                Some(acl) => acl.granted(&self.credentials, entry),
+                None => self.credentials.class_bits(entry.mode, entry.uid, entry.gid),
+            }
+        };
+        let missing = required & !granted;
+        if missing == 0 {
+            return Ok(());
+        }
+
+        let name = match entry.name.to_string() {
+            name if name.is_empty() => "/".to_string(),
+            name => name,
+        };
+        if missing & READ != 0 {
+            Err(FileError::NoPermissionToRead(name).into())
+        } else if missing & WRITE != 0 {
+            Err(FileError::NoPermissionToWrite(name).into())
+        } else {
+            Err(FileError::NoPermissionToExecute(name).into())
+        }
+    }
+
+    /// Checks that the current credentials may look up names in the directory `entry`.
+    ///
+    /// # Errors
+    /// Returns `FileError::NoPermissionToExecute` otherwise.
+    pub(crate) fn check_search(&self, entry: &DirEntry) -> Result<()> {
+        self.check_access(entry, EXECUTE)
+    }
+
+    /// Runs `f` with the credentials of the superuser and restores the current ones afterwards,
+    /// for bookkeeping that has to reach entries the caller can't.
+    pub(crate) fn as_root<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let credentials = self.credentials;
+        self.credentials = Credentials::root();
+        let result = f(self);
+        self.credentials = credentials;
+        result
+    }
+}
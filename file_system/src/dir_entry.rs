@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fmt::Debug;
+use std::ops::Range;
 
 use anyhow::Result;
 #[cfg(feature = "py-bindings")]
@@ -8,9 +9,21 @@ use serde_derive::{Deserialize, Serialize};
 
 use logger_macro::trace_log;
 
+use crate::acl::NO_ACL;
 use crate::errors::FileError;
+use crate::format::DirIndex;
+use crate::times;
 use crate::utils::fixed_str::FixedString;
-use crate::{FileSystem, READ_WRITE};
+use crate::xattr::NO_XATTRS;
+use crate::credentials::{ROOT_GID, ROOT_UID};
+use crate::{FileSystem, DIR_MODE, FILE_MODE, SYMLINK_MODE};
+
+/// Block number marking a slot that holds the continuation of the preceding entry's long name.
+///
+/// The FAT never hands out this block, so it can't clash with a real entry.
+/// On disk the slot holds the next piece of the name, in memory the full name
+/// lives in the first slot and the continuation slots are left blank.
+pub(crate) const LONG_NAME_BLK: u16 = u16::MAX;
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "py-bindings", pyclass)]
@@ -18,6 +31,17 @@ pub enum FileType {
     #[default]
     File,
     Directory,
+    /// A symbolic link, its data chain holds the target path.
+    Symlink,
+}
+
+/// The mode a new entry of `file_type` starts out with.
+pub(crate) fn default_mode(file_type: FileType) -> u16 {
+    match file_type {
+        FileType::File => FILE_MODE,
+        FileType::Directory => DIR_MODE,
+        FileType::Symlink => SYMLINK_MODE,
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
@@ -25,22 +49,65 @@ pub enum FileType {
 pub struct DirEntry {
     pub(crate) name: FixedString,
     pub(crate) file_type: FileType,
+    /// Length of a file or of a symlink target.
+    /// For a directory the sum of the sizes of every name below it,
+    /// so a file with several names counts once per name, `dir_usage` tells the space taken.
     pub(crate) size: u64,
     pub(crate) blk_num: u16,
-    pub(crate) access_level: u8,
+    /// Permission bits `rwxrwxrwx` for the owner, the group and everyone else.
+    pub(crate) mode: u16,
+    pub(crate) uid: u16,
+    pub(crate) gid: u16,
+    /// Number of entries sharing the data chain at `blk_num`, kept equal in all of them.
+    pub(crate) link_count: u16,
+    /// Creation, modification and access times in seconds since the Unix epoch, `0` if unknown.
+    pub(crate) created: i64,
+    pub(crate) modified: i64,
+    pub(crate) accessed: i64,
+    /// First block of the access control list, `NO_ACL` if the entry has none.
+    pub(crate) acl_blk: u16,
+    /// First block of the extended attributes, `NO_XATTRS` if the entry has none.
+    pub(crate) xattr_blk: u16,
 }
 
 impl DirEntry {
+    /// Creates an entry owned by the superuser with the default mode of its type.
     pub fn new(name: FixedString, file_type: FileType, size: u64, blk_num: u16) -> Self {
+        let now = times::now();
         DirEntry {
             name,
             file_type,
             size,
             blk_num,
-            access_level: READ_WRITE,
+            mode: default_mode(file_type),
+            uid: ROOT_UID,
+            gid: ROOT_GID,
+            link_count: 1,
+            created: now,
+            modified: now,
+            accessed: now,
+            acl_blk: NO_ACL,
+            xattr_blk: NO_XATTRS,
         }
     }
 
+    /// A blank slot reserved for the continuation of a long name.
+    pub(crate) fn name_continuation() -> Self {
+        DirEntry {
+            blk_num: LONG_NAME_BLK,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn is_name_continuation(&self) -> bool {
+        self.blk_num == LONG_NAME_BLK
+    }
+
+    /// Returns `true` if the slot is neither used by an entry nor by the continuation of a long name.
+    pub(crate) fn is_free(&self) -> bool {
+        self.name.is_empty() && !self.is_name_continuation()
+    }
+
     pub fn calculate_max_size() -> usize {
         let example_entry = Self::gen_max_size_entry();
         let serialized = bincode::serialize(&example_entry).unwrap();
@@ -61,7 +128,15 @@ impl DirEntry {
             file_type: FileType::File,
             size: u64::MAX,
             blk_num: u16::MAX,
-            access_level: u8::MAX,
+            mode: u16::MAX,
+            uid: u16::MAX,
+            gid: u16::MAX,
+            link_count: u16::MAX,
+            created: i64::MAX,
+            modified: i64::MAX,
+            accessed: i64::MAX,
+            acl_blk: u16::MAX,
+            xattr_blk: u16::MAX,
         }
     }
 }
@@ -75,6 +150,17 @@ pub struct DirBlock {
     pub(crate) parent_entry: DirEntry,
     #[serde(skip_deserializing, skip_serializing)]
     pub(crate) blk_num: u16,
+    /// The FAT chain holding the entries, starting at `blk_num`.
+    /// Each block stores `FileSystem::num_entries()` of the entries.
+    #[serde(skip_deserializing, skip_serializing)]
+    pub(crate) blocks: Vec<u16>,
+    /// How the entries are laid out over the blocks, chosen when the volume was formatted.
+    #[serde(skip_deserializing, skip_serializing)]
+    pub(crate) index: DirIndex,
+    /// Set if only this bucket of a hashed directory was read,
+    /// `blocks` and `entries` then only hold the bucket block and its slots.
+    #[serde(skip_deserializing, skip_serializing)]
+    pub(crate) bucket: Option<usize>,
     pub(crate) entries: Vec<DirEntry>,
 }
 
@@ -107,6 +193,9 @@ impl DirBlock {
             path: "".to_string(),
             parent_entry,
             blk_num,
+            blocks: vec![blk_num],
+            index: DirIndex::Linear,
+            bucket: None,
             entries,
         }
     }
@@ -121,6 +210,9 @@ impl DirBlock {
             path: "".to_string(),
             parent_entry: DirEntry::gen_max_size_entry(),
             blk_num: u16::MAX,
+            blocks: vec![u16::MAX],
+            index: DirIndex::Linear,
+            bucket: None,
             entries: vec![DirEntry::gen_max_size_entry(); FileSystem::num_entries()],
         }
     }
@@ -131,25 +223,73 @@ impl DirBlock {
         serialized.len()
     }
 
+    /// Returns the range of slots an entry with the given name can live in.
+    ///
+    /// For a linear directory this is every slot,
+    /// for a hashed directory it is the bucket block the name hashes to.
+    fn slot_range(&self, name: &FixedString) -> Range<usize> {
+        match self.index {
+            DirIndex::Hashed if self.bucket.is_none() => {
+                let per_block = FileSystem::num_entries();
+                let num_buckets = (self.entries.len() / per_block).max(1);
+                let bucket = bucket_of(name, num_buckets);
+                let start = (bucket * per_block).min(self.entries.len());
+                start..(start + per_block).min(self.entries.len())
+            }
+            _ => 0..self.entries.len(),
+        }
+    }
+
+    /// Finds the first run of free slots long enough for the given name.
+    fn find_free_slots(&self, name: &FixedString) -> Option<usize> {
+        let needed = slots_needed(name);
+        let mut run = 0;
+        for index in self.slot_range(name) {
+            if self.entries[index].is_free() {
+                run += 1;
+                if run == needed {
+                    return Some(index + 1 - needed);
+                }
+            } else {
+                run = 0;
+            }
+        }
+        None
+    }
+
     #[trace_log]
     pub fn get_entry(&self, name: &FixedString) -> Option<&DirEntry> {
-        self.entries.iter().find(|entry| entry.name == *name)
+        let range = self.slot_range(name);
+        self.entries[range].iter().find(|entry| entry.name == *name)
     }
 
     //#[trace_log]
     pub fn get_entry_mut(&mut self, name: &FixedString) -> Option<&mut DirEntry> {
-        self.entries
+        let range = self.slot_range(name);
+        self.entries[range]
             .iter_mut()
             .find(move |entry| entry.name == *name)
     }
 
+    /// Returns `true` if an entry with the given name can be added without growing the directory.
+    pub fn has_free_slot(&self, name: &FixedString) -> bool {
+        self.find_free_slots(name).is_some()
+    }
+
+    /// Adds an entry to the first free slot of the block.
+    /// Long names also reserve the slots following the entry.
+    /// Use `FileSystem::add_dir_entry` to grow the directory when it is full.
     #[trace_log]
     pub fn add_entry(&mut self, entry: DirEntry) -> Result<()> {
-        if let Some(index) = self.entries.iter().position(|item| item.name.is_empty()) {
+        if let Some(index) = self.find_free_slots(&entry.name) {
+            let needed = slots_needed(&entry.name);
             self.entries[index] = entry;
+            for slot in &mut self.entries[index + 1..index + needed] {
+                *slot = DirEntry::name_continuation();
+            }
             Ok(())
         } else {
-            Err(FileError::FileNotFound.into())
+            Err(FileError::DirectoryFull.into())
         }
     }
 
@@ -157,8 +297,16 @@ impl DirBlock {
     /// Get the block num before this as this will be deleted after this operation
     #[trace_log]
     pub fn remove_entry(&mut self, name: &FixedString) -> Result<()> {
-        if let Some(index) = self.entries.iter().position(|item| item.name == *name) {
-            self.entries[index] = DirEntry::default();
+        let range = self.slot_range(name);
+        if let Some(index) = self.entries[range.clone()]
+            .iter()
+            .position(|item| item.name == *name)
+        {
+            let start = range.start + index;
+            let needed = slots_needed(name);
+            for slot in &mut self.entries[start..start + needed] {
+                *slot = DirEntry::default();
+            }
             Ok(())
         } else {
             Err(FileError::FileNotFound.into())
@@ -167,11 +315,104 @@ impl DirBlock {
 
     #[trace_log]
     pub fn update_entry(&mut self, entry: &DirEntry) -> Result<()> {
-        if let Some(index) = self.entries.iter().position(|item| item.name == entry.name) {
-            self.entries[index] = entry.clone();
+        let range = self.slot_range(&entry.name);
+        if let Some(index) = self.entries[range.clone()]
+            .iter()
+            .position(|item| item.name == entry.name)
+        {
+            self.entries[range.start + index] = entry.clone();
             Ok(())
         } else {
             Err(FileError::FileNotFound.into())
         }
     }
+
+    /// Iterates over the used slots of the directory in on-disk order.
+    pub fn iter(&self) -> impl Iterator<Item = &DirEntry> {
+        self.entries.iter().filter(|entry| !entry.name.is_empty())
+    }
+
+    /// Returns the used slots of the directory sorted by name.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = &DirEntry> {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.value.cmp(&b.name.value));
+        entries.into_iter()
+    }
+
+    /// Moves the pieces of long names read from disk into the entries they belong to.
+    ///
+    /// Every continuation slot is appended to the closest used slot before it
+    /// and left blank, so lookups only ever see full names.
+    pub(crate) fn join_long_names(&mut self) {
+        let mut owner: Option<usize> = None;
+        for index in 0..self.entries.len() {
+            if self.entries[index].is_name_continuation() {
+                let piece = std::mem::take(&mut self.entries[index].name.value);
+                if let Some(owner) = owner {
+                    self.entries[owner].name.value.push_str(&piece);
+                }
+            } else if self.entries[index].name.is_empty() {
+                owner = None;
+            } else {
+                owner = Some(index);
+            }
+        }
+    }
+
+    /// Returns the slots as they are stored on disk,
+    /// with long names split over the entry and its continuation slots.
+    pub(crate) fn split_long_names(&self) -> Vec<DirEntry> {
+        let mut slots = self.entries.clone();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.is_name_continuation() {
+                continue;
+            }
+            for (offset, piece) in entry.name.chunks().into_iter().enumerate() {
+                slots[index + offset].name = piece.into();
+            }
+        }
+        slots
+    }
+
+    /// Redistributes the entries of a hashed directory over its current number of blocks.
+    ///
+    /// Returns `false` and leaves the directory untouched if a bucket would overflow.
+    pub(crate) fn rehash(&mut self) -> bool {
+        if self.index != DirIndex::Hashed {
+            return true;
+        }
+
+        let used = self.iter().cloned().collect::<Vec<_>>();
+        let mut rehashed = DirBlock {
+            entries: vec![DirEntry::default(); self.entries.len()],
+            index: self.index,
+            ..Default::default()
+        };
+
+        for entry in used {
+            if rehashed.add_entry(entry).is_err() {
+                return false;
+            }
+        }
+
+        self.entries = rehashed.entries;
+        true
+    }
+}
+
+/// The number of slots an entry with the given name takes up.
+fn slots_needed(name: &FixedString) -> usize {
+    name.chunks().len().max(1)
+}
+
+/// The bucket of a hashed directory with `num_buckets` blocks an entry with the given name lives in.
+pub(crate) fn bucket_of(name: &FixedString, num_buckets: usize) -> usize {
+    (hash_name(name) % num_buckets as u64) as usize
+}
+
+/// FNV-1a hash of an entry name, stable across platforms and compiler versions.
+pub(crate) fn hash_name(name: &FixedString) -> u64 {
+    name.value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
@@ -4,10 +4,12 @@ use prettytable::{format, row, Table};
 
 use crate::dir_entry::{DirBlock, DirEntry, FileType};
 use crate::errors::FileError;
+use crate::times::format_time;
 use crate::traits::Directory;
 use crate::utils::path_handler::{absolutize_from, split_path};
-use crate::{FileSystem, get_access_rights, READ, WRITE};
-use crate::utils::check_access_level;
+use crate::walk::{ReadDir, Walk};
+use crate::{FileSystem, READ, WRITE};
+use crate::utils::check_name;
 
 impl Directory for FileSystem {
     /// Creates a directory in the current directory
@@ -16,16 +18,10 @@ impl Directory for FileSystem {
         let abs_path = absolutize_from(path, &self.curr_block.path);
         let (parent, name) = split_path(abs_path.clone());
 
-        if name.len() > 55 {
-            return Err(FileError::FilenameTooLong.into());
-        } else if name.is_empty() {
-            return Err(FileError::InvalidFilename(name.to_string()).into());
-        }
+        check_name(&name)?;
 
-        let mut parent_block = self.traverse_dir(parent)?;
-        if !check_access_level(parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(name).into());
-        }
+        let mut parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        self.check_access(&parent_block.parent_entry, WRITE)?;
 
         match parent_block.get_entry(&name.clone().into()) {
             Some(entry) => {
@@ -36,12 +32,22 @@ impl Directory for FileSystem {
                 }
             }
             None => {
-                let new_entry =
+                let mut new_entry =
                     DirEntry::new(name.into(), FileType::Directory, 0, self.get_free_block()?);
-                let new_block = DirBlock::new(new_entry.clone(), new_entry.blk_num);
+                new_entry.uid = self.credentials.uid;
+                new_entry.gid = self.credentials.gid;
+                new_entry.acl_blk =
+                    self.inherit_acl(&parent_block.parent_entry, FileType::Directory)?;
+                let mut new_block = DirBlock::new(new_entry.clone(), new_entry.blk_num);
+                new_block.index = self.fat.options.dir_index;
                 self.write_data::<DirBlock>(&new_block, new_entry.blk_num)?;
-                parent_block.add_entry(new_entry)?;
+                if let Err(e) = self.add_dir_entry(&mut parent_block, new_entry.clone()) {
+                    self.release_side_blocks(&new_entry)?;
+                    self.clear_file_data(new_entry.blk_num)?;
+                    return Err(e);
+                }
                 self.update_dir(&mut parent_block, abs_path)?;
+                self.touch_modified(&parent)?;
             }
         }
 
@@ -54,12 +60,9 @@ impl Directory for FileSystem {
         let abs_path = absolutize_from(path, &self.curr_block.path);
         let (parent, name) = split_path(abs_path.clone());
 
-        let mut parent_block = self.traverse_dir(parent.clone())?;
-        if !check_access_level(parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(name).into());
-        }
-        let binding = parent_block.clone();
-        let entry = binding
+        let parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        self.check_access(&parent_block.parent_entry, WRITE)?;
+        let entry = parent_block
             .get_entry(&name.into())
             .ok_or(FileError::FileNotFound)?;
 
@@ -67,45 +70,58 @@ impl Directory for FileSystem {
             return Err(FileError::NotADirectory(path.into()).into());
         }
 
+        self.check_removable(entry)?;
         self.remove_dir_data(entry, path)?;
+        // dropping hard links may have rewritten the parent
+        let mut parent_block = self.traverse_bucket(parent.clone(), &entry.name.to_string())?;
         parent_block.remove_entry(&entry.name)?;
         self.write_dir_block(&parent_block)?;
+        self.update_dir_sizes(parent.clone(), -(entry.size as i64))?;
+        self.touch_modified(&parent)?;
         Ok(())
     }
 
+    /// Prints the entries of the current directory as a table.
     #[trace_log]
     fn list_dir(&mut self) -> Result<()> {
-
-        // Check if we have read permission for the current directory
-        if !check_access_level(self.curr_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(self.curr_block.path.clone()).into());
-        }
+        let entries = self.read_dir(&self.cwd())?;
 
         let mut table = Table::new();
         table.set_titles(row![
             "Name".to_string(),
             "Type".to_string(),
             "Size (Bytes)".to_string(),
+            "Links".to_string(),
             "Block Number".to_string(),
             "Access Rights".to_string(),
+            "Owner".to_string(),
+            "Group".to_string(),
+            "Modified".to_string(),
         ]);
 
         // Print each entry with dynamic column widths and explicit padding
-        for entry in &self.curr_block.entries {
-            if !entry.name.is_empty() {
-                let entry_type = match entry.file_type {
-                    FileType::File => "File",
-                    FileType::Directory => "Directory",
-                };
-
-                table.add_row(row![
-                    entry.name.to_string(),
-                    entry_type.to_string(),
-                    entry.size.to_string(),
-                    entry.blk_num.to_string(),
-                    get_access_rights(entry.access_level).to_string(),
-                ]);
-            }
+        for entry in entries {
+            let entry_type = match entry.file_type {
+                FileType::File => "File",
+                FileType::Directory => "Directory",
+                FileType::Symlink => "Symlink",
+            };
+            let name = match &entry.target {
+                Some(target) => format!("{} -> {}", entry.name, target),
+                None => entry.name.clone(),
+            };
+
+            table.add_row(row![
+                name,
+                entry_type.to_string(),
+                entry.size.to_string(),
+                entry.link_count.to_string(),
+                entry.blk_num.to_string(),
+                entry.permissions().to_string(),
+                entry.uid.to_string(),
+                entry.gid.to_string(),
+                format_time(entry.modified),
+            ]);
         }
 
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
@@ -114,4 +130,28 @@ impl Directory for FileSystem {
 
         Ok(())
     }
+
+    /// Returns the entries of the directory at `path` in listing order,
+    /// symbolic links are followed to the directory.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotADirectory` if the path names something else
+    /// and `FileError::NoPermissionToRead` without read permission on the directory.
+    #[trace_log]
+    fn read_dir(&self, path: &str) -> Result<ReadDir<'_>> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::Directory {
+            return Err(FileError::NotADirectory(path.into()).into());
+        }
+
+        let block = self.traverse_dir(abs_path)?;
+        self.check_access(&block.parent_entry, READ)?;
+        Ok(ReadDir::new(self, block))
+    }
+
+    /// Walks the tree below `path`, see `Walk` for the order and the options.
+    fn walk(&self, path: &str) -> Walk<'_> {
+        Walk::new(self, path)
+    }
 }
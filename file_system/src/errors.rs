@@ -12,6 +12,12 @@ pub enum FSError {
     NoFreeBlocks,
     #[error("Error reading block")]
     InvalidBlockReference,
+    #[error("Unsupported volume version: {0}, please reformat the disk")]
+    UnsupportedVersion(u16),
+    #[error("Invalid format option: {0}")]
+    InvalidFormatOption(String),
+    #[error("Invalid access time policy: {0}, expected strict, relatime or noatime")]
+    InvalidAtimePolicy(String),
     #[error("Python error: {0}")]
     PyError(String),
     #[error("Embeded Python not supported on this platform, please see https://pyo3.rs/v0.20.2/building_and_distribution.html?highlight=pypy%20embeded#dynamically-embedding-the-python-interpreter for more information.\nIt might work in certain cases but its hard to support them all sadly. A new feature might be added in the future to allow to compile anyway but this will never be used in the precompiled versions!")]
@@ -36,8 +42,36 @@ pub enum FileError {
     FileExists(FixedString),
     #[error("Directory already exists with name: {0}")]
     DirectoryExists(FixedString),
-    #[error("Invalid access level: {0}")]
-    InvalidAccessLevel(u8),
+    #[error("Not a symbolic link: {0}")]
+    NotASymlink(String),
+    #[error("Too many levels of symbolic links: {0}")]
+    TooManySymlinks(String),
+    #[error("Cannot copy or move a directory into itself: {0}")]
+    IntoItself(String),
+    #[error("Directory not empty: {0}")]
+    DirectoryNotEmpty(String),
+    #[error("Directory is full")]
+    DirectoryFull,
+    #[error("File would grow past {max} bytes", max = crate::MAX_FILE_SIZE)]
+    FileTooLarge,
+    #[error("Invalid access level: {0}, expected an octal mode like 755 or clauses like u+x,g-w")]
+    InvalidAccessLevel(String),
+    #[error("Invalid ACL entry: {0}, expected [default:][deny:]user|group:id:rwx")]
+    InvalidAcl(String),
+    #[error("Invalid extended attribute: {0}, expected a name of 1 to 255 bytes and a value of up to 64 KiB")]
+    InvalidXattr(String),
+    #[error("No such extended attribute: {0}")]
+    XattrNotFound(String),
+    #[error("Invalid base64: {0}")]
+    InvalidBase64(String),
+    #[error("Invalid open options: {0}")]
+    InvalidOpenOptions(String),
+    #[error("File is not open for {0}")]
+    NotOpenFor(String),
+    #[error("Operation not permitted, not the owner of: {0}")]
+    NotOwner(String),
+    #[error("Operation not permitted: {0}")]
+    NotPermitted(String),
     #[error("No premission to write to file: {0}")]
     NoPermissionToWrite(String),
     #[error("No premission to read file: {0}")]
@@ -48,6 +82,20 @@ pub enum FileError {
     PyError(String),
 }
 
+#[derive(Error, Debug)]
+pub enum UserError {
+    #[error("No such user: {0}")]
+    UserNotFound(String),
+    #[error("User already exists: {0}")]
+    UserExists(String),
+    #[error("Invalid user name: {0}, expected up to 32 lowercase letters, digits, '_' or '-'")]
+    InvalidUsername(String),
+    #[error("Authentication failed for: {0}")]
+    AuthenticationFailed(String),
+    #[error("Error hashing password: {0}")]
+    PasswordHash(String),
+}
+
 #[derive(Error, Debug)]
 pub enum IOHandlerError {
     #[error("Input/Output error: {0}")]
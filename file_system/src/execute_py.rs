@@ -12,8 +12,7 @@ use {
     crate::{READ, EXECUTE},
     crate::dir_entry::FileType,
     crate::errors::FileError,
-    crate::utils::check_access_level,
-    crate::utils::path_handler::{absolutize_from, split_path},
+    crate::utils::path_handler::split_path,
 };
 use logger_macro::trace_log;
 
@@ -23,7 +22,7 @@ pub fn run_code(code: String) -> PyResult<()> {
     Python::with_gil(|py| {
         let locals = PyDict::new(py);
         // get globals from the current python environment
-        let globals = py.eval("globals()", None, Some(&locals))?;
+        let globals = py.eval("globals()", None, Some(locals))?;
         // convert the globals to a dictionary using PyTryFrom
         let globals = <PyDict as PyTryFrom>::try_from(globals)?;
 
@@ -51,21 +50,14 @@ impl FileSystem {
                 code_to_run = input.trim_matches('"').replace("\\n", "\n").to_string();
 
                 // Check if we have execute permissions in the current directory
-                let current_dir = &self.curr_block;
-                if !check_access_level(current_dir.parent_entry.access_level, EXECUTE) {
-                    return Err(FileError::NoPermissionToExecute(current_dir.clone().path).into());
-                }
+                self.check_access(&self.curr_block.parent_entry, EXECUTE)?;
             } else {
                 // It's a virtual file path, process it
-                let abs_path = absolutize_from(input, &self.curr_block.path);
+                let abs_path = self.resolve_path(input, true)?;
                 let (parent, name_extracted) = split_path(abs_path.clone());
                 name = name_extracted; // Save the name for permission checks
 
-                let parent_block = self.traverse_dir(parent)?;
-
-                if !check_access_level(parent_block.parent_entry.access_level, READ) {
-                    return Err(FileError::NoPermissionToWrite(name.clone()).into());
-                }
+                let parent_block = self.traverse_bucket(parent, &name)?;
 
                 let entry = parent_block.get_entry(&name.clone().into()).ok_or(FileError::FileNotFound)?;
 
@@ -73,11 +65,10 @@ impl FileSystem {
                     return Err(FileError::FileIsDirectory.into());
                 }
 
-                if !check_access_level(entry.access_level, EXECUTE) {
-                    return Err(FileError::NoPermissionToExecute(name.clone()).into());
-                }
+                // a script has to be read to be run
+                self.check_access(entry, READ | EXECUTE)?;
 
-                code_to_run = self.read_file_data(entry.blk_num)?.into();
+                code_to_run = String::from_utf8(self.read_content(entry)?)?;
             }
 
             // Execute the Python code
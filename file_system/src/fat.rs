@@ -1,10 +1,15 @@
 #![allow(clippy::upper_case_acronyms)]
 #![allow(unused_variables)]
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fmt::Debug;
 use std::ops::{Index, IndexMut};
 
-use serde_derive::{Deserialize, Serialize};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserializer, Serializer};
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "py-bindings")]
 use pyo3::prelude::*;
@@ -12,6 +17,8 @@ use pyo3::prelude::*;
 use logger_macro::trace_log;
 use rustic_disk::Disk;
 
+use crate::format::FormatOptions;
+
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 //#[cfg_attr(feature = "py-bindings", pyclass)]
 pub enum FatType {
@@ -20,27 +27,56 @@ pub enum FatType {
     EOF,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+/// Marks a FAT block written in the packed format.
+/// The legacy format starts with the number of entries instead, which is never this large.
+const FAT_MAGIC: u64 = u64::MAX;
+
+/// Version of the on-disk layout, bumped whenever the layout of `DirEntry`
+/// or of the data it points to changes.
+/// Volumes written by an older version are migrated when they are opened.
+pub(crate) const FAT_VERSION: u16 = 8;
+
+/// First version storing file content block-aligned instead of as a serialized `FileData`.
+pub(crate) const RAW_CONTENT_VERSION: u16 = 7;
+
+/// First version storing the holes of sparse files behind the FAT links.
+pub(crate) const SPARSE_VERSION: u16 = 8;
+
+/// Most links of the whole volume that can be followed by a hole,
+/// the hole records share the FAT block with the links.
+pub(crate) const MAX_HOLES: usize = 192;
+
+const PACKED_FREE: u16 = u16::MAX;
+const PACKED_EOF: u16 = u16::MAX - 1;
+
+#[derive(Clone)]
 #[cfg_attr(feature = "py-bindings", pyclass)]
-pub struct FAT(
-    //#[serde(with = "BigArray")]
-    Vec<FatType>,
-);
+pub struct FAT {
+    entries: Vec<FatType>,
+    /// Number of zero blocks a file skips between a block and the next block of its chain,
+    /// only recorded for links followed by a hole. A hole at the end of a file needs no record,
+    /// the size of the file tells how far it reaches past the last block.
+    holes: BTreeMap<u16, u64>,
+    /// Options chosen when the volume was formatted, persisted in the FAT block.
+    pub(crate) options: FormatOptions,
+    /// Layout version of the volume, 0 for the legacy FAT encoding.
+    pub(crate) version: u16,
+}
 
 impl Debug for FAT {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // get number of free blocks
-        let num_free = self.0.iter().filter(|&x| *x == FatType::Free).count();
+        let num_free = self.entries.iter().filter(|&x| *x == FatType::Free).count();
         // get number of EOF blocks
-        let num_eof = self.0.iter().filter(|&x| *x == FatType::EOF).count();
+        let num_eof = self.entries.iter().filter(|&x| *x == FatType::EOF).count();
         // get number of taken blocks
         let num_taken = self
-            .0
+            .entries
             .iter()
             .filter(|&x| matches!(x, FatType::Taken(_)))
             .count();
         // get number of blocks
-        let num_blocks = self.0.len();
+        let num_blocks = self.entries.len();
         write!(
             f,
             "FAT{{Free: {}, Taken: {}, EOF: {}, Total: {}}}",
@@ -54,12 +90,24 @@ impl FAT {
     pub fn new() -> Self {
         let mut fat = vec![FatType::Free; (Disk::BLOCK_SIZE >> 2) - 8]; // 8 bytes is from padding in FAT struct
         fat.fill(FatType::Free);
-        FAT(fat)
+        FAT {
+            entries: fat,
+            holes: BTreeMap::new(),
+            options: FormatOptions::default(),
+            version: FAT_VERSION,
+        }
+    }
+
+    pub fn with_options(options: FormatOptions) -> Self {
+        FAT {
+            options,
+            ..Self::new()
+        }
     }
 
     // Create an iterator
     #[trace_log]
-    pub fn iter(&self) -> FatIterator {
+    pub fn iter(&self) -> FatIterator<'_> {
         FatIterator {
             fat: self,
             position: 0,
@@ -68,7 +116,31 @@ impl FAT {
 
     #[trace_log]
     pub fn get(&self, index: usize) -> Option<&FatType> {
-        self.0.get(index)
+        self.entries.get(index)
+    }
+
+    /// Returns the number of blocks tracked by the FAT.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of zero blocks between `blk` and the next block of its chain.
+    pub(crate) fn holes_after(&self, blk: u16) -> u64 {
+        self.holes.get(&blk).copied().unwrap_or(0)
+    }
+
+    /// Records that `holes` zero blocks come between `blk` and the next block of its chain.
+    pub(crate) fn set_holes_after(&mut self, blk: u16, holes: u64) {
+        if holes == 0 {
+            self.holes.remove(&blk);
+        } else {
+            self.holes.insert(blk, holes);
+        }
+    }
+
+    /// Returns `true` if one more link can be followed by a hole.
+    pub(crate) fn can_add_hole(&self) -> bool {
+        self.holes.len() < MAX_HOLES
     }
 }
 
@@ -82,13 +154,13 @@ impl Index<usize> for FAT {
     type Output = FatType;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.entries[index]
     }
 }
 
 impl IndexMut<usize> for FAT {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+        &mut self.entries[index]
     }
 }
 
@@ -103,12 +175,117 @@ impl<'a> Iterator for FatIterator<'a> {
     type Item = &'a FatType;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.position >= Disk::NUM_BLOCKS {
+        if self.position >= self.fat.len() {
             None
         } else {
-            let result = &self.fat.0[self.position];
+            let result = &self.fat.entries[self.position];
             self.position += 1;
             Some(result)
         }
     }
 }
+
+impl FatType {
+    fn pack(self) -> u16 {
+        match self {
+            FatType::Free => PACKED_FREE,
+            FatType::EOF => PACKED_EOF,
+            FatType::Taken(next_blk) => next_blk,
+        }
+    }
+
+    fn unpack(value: u16) -> Self {
+        match value {
+            PACKED_FREE => FatType::Free,
+            PACKED_EOF => FatType::EOF,
+            next_blk => FatType::Taken(next_blk),
+        }
+    }
+}
+
+/// The FAT is stored as two bytes per block behind a small header,
+/// the legacy encoding of one `FatType` enum per block only fits a handful of `Taken` links.
+impl Serialize for FAT {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let packed = self.entries.iter().map(|entry| entry.pack()).collect::<Vec<_>>();
+        let mut tuple = serializer.serialize_tuple(5)?;
+        tuple.serialize_element(&FAT_MAGIC)?;
+        tuple.serialize_element(&self.version)?;
+        tuple.serialize_element(&self.options)?;
+        tuple.serialize_element(&packed)?;
+        tuple.serialize_element(&self.holes)?;
+        tuple.end()
+    }
+}
+
+struct FATVisitor;
+
+impl<'de> Visitor<'de> for FATVisitor {
+    type Value = FAT;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a packed or legacy FAT")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let first: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+        if first != FAT_MAGIC {
+            // legacy format, `first` is the number of entries
+            let mut entries = Vec::with_capacity(first as usize);
+            for index in 0..first as usize {
+                let entry = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(index + 1, &self))?;
+                entries.push(entry);
+            }
+            return Ok(FAT {
+                entries,
+                holes: BTreeMap::new(),
+                options: FormatOptions::default(),
+                version: 0,
+            });
+        }
+
+        let version: u16 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let options: FormatOptions = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        let packed: Vec<u16> = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+        let holes = if version >= SPARSE_VERSION {
+            seq.next_element()?
+                .ok_or_else(|| de::Error::invalid_length(4, &self))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(FAT {
+            entries: packed.into_iter().map(FatType::unpack).collect(),
+            holes,
+            options,
+            version,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for FAT {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // the real length depends on the format, the visitor stops reading on its own
+        deserializer.deserialize_tuple(usize::MAX, FATVisitor)
+    }
+}
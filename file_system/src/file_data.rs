@@ -1,4 +1,5 @@
 use std::ops::Add;
+use std::string::FromUtf8Error;
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -51,9 +52,12 @@ impl From<FileData> for Vec<u8> {
     }
 }
 
-impl From<FileData> for String {
-    fn from(data: FileData) -> Self {
-        String::from_utf8(data.data).unwrap()
+impl TryFrom<FileData> for String {
+    type Error = FromUtf8Error;
+
+    /// Fails instead of panicking if the data isn't valid UTF-8.
+    fn try_from(data: FileData) -> Result<Self, Self::Error> {
+        String::from_utf8(data.data)
     }
 }
 
@@ -1,7 +1,10 @@
 #![allow(unused_variables)]
 use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 
 use std::fmt::Debug;
+use std::io::{Cursor, Read};
 
 #[cfg(feature = "debug")]
 use log::{debug, trace};
@@ -10,13 +13,14 @@ use logger_macro::trace_log;
 
 use crate::dir_entry::{DirEntry, FileType};
 use crate::errors::FileError;
-use crate::file_data::FileData;
 use crate::prelude::Input;
 use crate::tests::MockInput;
+use crate::times::now;
 use crate::traits::{File, IOHandler};
-use crate::utils::path_handler::{absolutize_from, split_path};
-use crate::{FileSystem, READ, READ_WRITE, StdIOHandler, WRITE};
-use crate::utils::check_access_level;
+use crate::utils::path_handler::{absolutize_from, anchor, split_path};
+use crate::{FileSystem, FILE_MODE, READ, StdIOHandler, WRITE};
+use crate::utils::{check_name, hex};
+use crate::xattr::NO_XATTRS;
 
 pub struct StdinInput {
     io: StdIOHandler,
@@ -35,36 +39,69 @@ impl Debug for StdinInput {
 }
 
 impl Input for StdinInput {
-    fn read_lines(&mut self) -> Result<String> {
+    /// Reads lines until an empty one, joined by line breaks.
+    fn read_data(&mut self) -> Result<Vec<u8>> {
         // Use the read method from IOHandler trait implemented by StdIOHandler
         let mut data = String::new();
 
         loop {
-            match self.io.read() {
-                Ok(line) => {
-                    if line.is_empty() {
-                        break; // Exit loop if the line is empty
-                    }
-                    data.push_str(&line);
-                    data.push('\n');
-                }
-                Err(e) => return Err(e), // Propagate error
+            let line = self.io.read()?;
+            if line.is_empty() {
+                break; // Exit loop if the line is empty
             }
+            data.push_str(&line);
+            data.push('\n');
         }
 
         if data.ends_with('\n') {
             data.pop(); // Remove the last newline character if it exists
         }
 
-        Ok(data)
+        Ok(data.into_bytes())
     }
 }
 
-impl File for FileSystem {
-    /// # Create a file in the current directory
+impl FileSystem {
+    /// Replaces the content of the file at `path` with `content`
+    /// and updates the size and modification time of every name of it.
+    /// Returns the first block of the new content.
     ///
-    //#[trace_log]
-    fn create_file<T: Input + Debug>(&mut self, path: &str, input_source: &mut T) -> Result<()> {
+    /// The content is written to a new chain that only replaces the old one once it is complete,
+    /// so the file keeps its old content if the volume runs out of space.
+    /// The caller checks that it may write the file.
+    #[trace_log]
+    pub(crate) fn replace_file_data(&mut self, path: &str, content: &[u8]) -> Result<u16> {
+        let abs_path = self.resolve_path(&anchor(path), false)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
+        }
+
+        let blk_num = self.get_free_block()?;
+        if let Err(e) = self.write_content_from(blk_num, &mut &content[..]) {
+            self.clear_file_data(blk_num)?;
+            return Err(e);
+        }
+
+        if let Err(e) = self.update_entry_at(&abs_path, |link| link.blk_num = blk_num) {
+            self.clear_file_data(blk_num)?;
+            return Err(e);
+        }
+        self.clear_file_data(entry.blk_num)?;
+        self.set_file_size(abs_path, &DirEntry { blk_num, ..entry }, content.len() as u64)?;
+        Ok(blk_num)
+    }
+
+    /// Creates a file at `path` holding everything the reader `open` returns yields,
+    /// streamed block by block so only one block is held in memory.
+    ///
+    /// `open` is only called once the file may be created.
+    /// If reading fails, the blocks written so far are freed and no file is created.
+    fn create_file_streamed<R: Read>(
+        &mut self,
+        path: &str,
+        open: impl FnOnce() -> Result<R>,
+    ) -> Result<()> {
         let abs_path = absolutize_from(path, &self.curr_block.path);
         let (parent, name) = split_path(abs_path.clone());
 
@@ -76,19 +113,12 @@ impl File for FileSystem {
             debug!("Name: {}", name);
         }
 
-        // Controls so that the length isn´t longer than 55 chars
-        if name.len() > 55 {
-            return Err(FileError::FilenameTooLong.into());
-        } else if name.is_empty() {
-            return Err(FileError::InvalidFilename(name.to_string()).into());
-        }
+        check_name(&name)?;
 
-        let mut parent_block = self.traverse_dir(parent)?;
+        let mut parent_block = self.traverse_bucket(parent.clone(), &name)?;
 
         //check if we have write permission
-        if !check_access_level(parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(name).into());
-        }
+        self.check_access(&parent_block.parent_entry, WRITE)?;
 
         // make code to check if file exists and parent exists
         for entry in parent_block.entries.iter() {
@@ -97,15 +127,7 @@ impl File for FileSystem {
             }
         }
 
-        // read data from user
-        let data = input_source.read_lines()?;
-
-        #[cfg(feature = "debug")]
-        {
-            debug!("Data: {}", data);
-        }
-
-        let file_data = FileData::from(data);
+        let mut reader = open()?;
 
         // find the first free block
         let blk_num = self.get_free_block()?;
@@ -114,17 +136,31 @@ impl File for FileSystem {
         {
             trace!("Writing file data");
             debug!("Free block: {}", blk_num);
-            debug!("Data size on disk: {}", file_data.get_size());
         }
 
-        self.write_data(&file_data, blk_num)?;
+        let size = match self.write_content_from(blk_num, &mut reader) {
+            Ok(size) => size,
+            Err(e) => {
+                self.clear_file_data(blk_num)?;
+                return Err(e);
+            }
+        };
 
+        let now = now();
         let entry = DirEntry {
             name: name.into(),
             file_type: FileType::File,
-            size: file_data.get_size() as u64,
+            size,
             blk_num,
-            access_level: READ_WRITE,
+            mode: FILE_MODE,
+            uid: self.credentials.uid,
+            gid: self.credentials.gid,
+            link_count: 1,
+            created: now,
+            modified: now,
+            accessed: now,
+            acl_blk: self.inherit_acl(&parent_block.parent_entry, FileType::File)?,
+            xattr_blk: NO_XATTRS,
         };
 
         #[cfg(feature = "debug")]
@@ -134,18 +170,131 @@ impl File for FileSystem {
         }
 
         // update size of the parent block
-        parent_block.add_entry(entry)?;
+        if let Err(e) = self.add_dir_entry(&mut parent_block, entry.clone()) {
+            self.release_side_blocks(&entry)?;
+            self.clear_file_data(blk_num)?;
+            return Err(e);
+        }
         self.update_dir(&mut parent_block, abs_path)?;
+        self.touch_modified(&parent)?;
 
         Ok(())
     }
 
+    /// Sets the size of the file `entry` at the absolute `abs_path` after its content changed,
+    /// every name of it shows the new size and modification time.
+    #[trace_log]
+    fn set_file_size(&mut self, abs_path: String, entry: &DirEntry, size: u64) -> Result<()> {
+        let modified = now();
+        let delta = size as i64 - entry.size as i64;
+
+        if entry.link_count > 1 {
+            // every name of the file has to show the new size
+            for parent in self.update_links(entry.blk_num, |link| {
+                link.size = size;
+                link.modified = modified;
+            })? {
+                self.update_dir_sizes(parent, delta)?;
+            }
+        } else {
+            self.update_entry_at(&abs_path, |entry| {
+                entry.size = size;
+                entry.modified = modified;
+            })?;
+            self.update_dir_sizes(split_path(abs_path).0, delta)?;
+        }
+        Ok(())
+    }
+}
+
+impl File for FileSystem {
+    /// # Create a file in the current directory
+    ///
+    //#[trace_log]
+    fn create_file<T: Input + Debug>(&mut self, path: &str, input_source: &mut T) -> Result<()> {
+        // read data from user, only once the file may be created
+        self.create_file_streamed(path, || {
+            let data = input_source.read_data()?;
+
+            #[cfg(feature = "debug")]
+            {
+                debug!("Data: {:?}", data);
+            }
+
+            Ok(Cursor::new(data))
+        })
+    }
+
+    /// Creates a file holding everything `reader` yields,
+    /// written block by block as it is read instead of being collected first.
+    ///
+    /// # Errors
+    /// Returns the error of the reader if reading fails, the file isn't created then.
+    fn create_file_from_reader<R: Read>(&mut self, path: &str, reader: R) -> Result<()> {
+        self.create_file_streamed(path, || Ok(reader))
+    }
+
+    /// Replaces the content of the file at `path` with everything `reader` yields,
+    /// creating the file if it doesn't exist.
+    ///
+    /// The content is streamed into new blocks and only swapped in once reading finished,
+    /// if reading fails the file keeps its old content.
+    /// Every name of the file shows the new content.
+    fn write_file_from_reader<R: Read>(&mut self, path: &str, mut reader: R) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = match self.lookup_entry(&abs_path, false) {
+            Ok(entry) => entry,
+            Err(e) if matches!(e.downcast_ref(), Some(FileError::FileNotFound)) => {
+                return self.create_file_from_reader(&abs_path, reader);
+            }
+            Err(e) => return Err(e),
+        };
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
+        }
+        self.check_access(&entry, WRITE)?;
+
+        let blk_num = self.get_free_block()?;
+        let size = match self.write_content_from(blk_num, &mut reader) {
+            Ok(size) => size,
+            Err(e) => {
+                self.clear_file_data(blk_num)?;
+                return Err(e);
+            }
+        };
+
+        self.update_entry_at(&abs_path, |link| link.blk_num = blk_num)?;
+        self.clear_file_data(entry.blk_num)?;
+        self.set_file_size(abs_path, &DirEntry { blk_num, ..entry }, size)
+    }
+
     fn create_file_with_content(&mut self, path: &str, content: &str) -> anyhow::Result<()> {
+        self.create_file_with_bytes(path, content.as_bytes())
+    }
+
+    fn create_file_with_bytes(&mut self, path: &str, content: &[u8]) -> anyhow::Result<()> {
         let mut data = MockInput::new(content);
         self.create_file(path, &mut data)?;
         Ok(())
     }
 
+    /// Creates a file holding the bytes `encoded` in standard base64 decodes to,
+    /// line breaks and other whitespace in it are skipped.
+    ///
+    /// # Errors
+    /// Returns `FileError::InvalidBase64` if `encoded` isn't valid base64.
+    #[trace_log]
+    fn create_file_from_base64(&mut self, path: &str, encoded: &str) -> anyhow::Result<()> {
+        let encoded = encoded
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        let content = STANDARD
+            .decode(encoded)
+            .map_err(|e| FileError::InvalidBase64(e.to_string()))?;
+        self.create_file_with_bytes(path, &content)
+    }
+
     fn create_file_stdio(&mut self, name: &str) -> anyhow::Result<()> {
         let mut data = StdinInput::new(StdIOHandler);
         self.create_file(name, &mut data)?;
@@ -157,138 +306,175 @@ impl File for FileSystem {
         let abs_path = absolutize_from(path, &self.curr_block.path);
         let (parent, name) = split_path(abs_path.clone());
 
-        let mut parent_block = self.traverse_dir(parent.clone())?;
-
-        if !check_access_level(parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(name).into());
-        }
+        let mut parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        self.check_access(&parent_block.parent_entry, WRITE)?;
 
         let binding = parent_block.clone();
         let entry = binding
             .get_entry(&name.into())
             .ok_or(FileError::FileNotFound)?;
 
-        self.clear_file_data(entry.blk_num)?;
         parent_block.remove_entry(&entry.name)?;
-
-        self.fat[entry.blk_num as usize] = crate::fat::FatType::Free;
-
         self.write_dir_block(&parent_block)?;
+        self.release_data(entry)?;
+        self.update_dir_sizes(parent.clone(), -(entry.size as i64))?;
+        self.touch_modified(&parent)?;
         Ok(())
     }
 
     /// the cat function
+    ///
+    /// Bytes that aren't valid UTF-8 are shown as replacement characters, `hexdump` shows them as they are.
     #[trace_log]
     fn read_file(&mut self, path: &str) -> anyhow::Result<()> {
-        let abs_path = absolutize_from(path, &self.curr_block.path);
-        let (parent, name) = split_path(abs_path);
-
-        let parent_block = self.traverse_dir(parent.clone())?;
-
-        //check if we have read permission
-        if !check_access_level(parent_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(name).into());
-        }
+        let data = self.read(path)?;
+        let data = String::from_utf8_lossy(&data).into_owned();
 
         #[cfg(feature = "debug")]
         {
-            debug!("Path: {}", &path);
-            debug!("Parent: {}", &parent);
-            debug!("Name: {}", &name);
-        }
-
-        if name.is_empty() {
-            return Err(FileError::InvalidFilename(name.to_string()).into());
-        }
-
-        let mut file_entry: &DirEntry = &DirEntry::default();
-
-        for entry in parent_block.entries.iter() {
-            if entry.name == name.clone().into() {
-                file_entry = entry;
-            }
+            debug!("Data: {}", data);
         }
 
-        if file_entry == &DirEntry::default() {
-            return Err(FileError::FileNotFound.into());
-        }
+        self.io_handler.write(data)?;
+        Ok(())
+    }
 
-        // make sure it's of type file
-        if file_entry.file_type != FileType::File {
+    /// Returns the whole content of the file at `path`, symbolic links are followed.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileIsDirectory` if the path doesn't name a file
+    /// and `FileError::NoPermissionToRead` without read permission on it.
+    #[trace_log]
+    fn read(&mut self, path: &str) -> Result<Vec<u8>> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
             return Err(FileError::FileIsDirectory.into());
         }
+        self.check_access(&entry, READ)?;
 
-        if !check_access_level(file_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(name).into());
-        }
-
-        // recursivly check the fat until we reach EOF and read all blocks in order
-        let data = self.read_file_data(file_entry.blk_num)?;
-        let data = String::from_utf8(data.data)?;
+        let data = self.read_content(&entry)?;
+        self.touch_accessed(&abs_path)?;
+        Ok(data)
+    }
 
-        #[cfg(feature = "debug")]
-        {
-            debug!("Data: {}", data);
+    /// Appends the content of the file at `source` to the file at `dest`, nothing is put between them.
+    #[trace_log]
+    fn append_file(&mut self, source: &str, dest: &str) -> anyhow::Result<()> {
+        let abs_src = self.resolve_path(source, true)?;
+        let src_entry = self.lookup_entry(&abs_src, false)?;
+        if src_entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
         }
 
-        //println!("{}", data);
-        self.io_handler.write(data)?;
+        //check if we have read permission for the source, append_bytes checks the destination
+        self.check_access(&src_entry, READ)?;
+        let content = self.read_content(&src_entry)?;
+        self.append_bytes(dest, &content)?;
 
+        self.touch_accessed(&abs_src)?;
         Ok(())
     }
 
-    /// The append function
+    /// Appends `data` to the file at `path` in place,
+    /// only its last block and the blocks for the overflow are written.
     #[trace_log]
-    fn append_file(&mut self, source: &str, dest: &str) -> anyhow::Result<()> {
-        let abs_src = absolutize_from(source, &self.curr_block.path);
-        let abs_dest = absolutize_from(dest, &self.curr_block.path);
-
-        let (src_parent, src_name) = split_path(abs_src);
-        let (dest_parent, dest_name) = split_path(abs_dest.clone());
-
-        let src_block = self.traverse_dir(src_parent)?;
-        let mut dest_block = self.traverse_dir(dest_parent)?;
+    fn append_bytes(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
+        }
+        self.check_access(&entry, WRITE)?;
+
+        let size = entry
+            .size
+            .checked_add(data.len() as u64)
+            .ok_or(FileError::FileTooLarge)?;
+        self.write_content_at(&entry, entry.size, data)?;
+        self.set_file_size(abs_path, &entry, size)
+    }
 
-        //check if we have write permission for destnation and read permission for source
-        if !check_access_level(src_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(dest_name).into());
+    /// Writes `data` into the file at `path` at `offset` in place, growing the file if it reaches past the end.
+    ///
+    /// Writing past the end leaves a hole that reads as zeros and takes no blocks,
+    /// so a file can be given a large size without allocating everything before the written data.
+    /// Writing nothing leaves the file as it is, wherever `offset` points.
+    #[trace_log]
+    fn write_at(&mut self, path: &str, offset: u64, data: &[u8]) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
         }
-        if !check_access_level(dest_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(dest_name).into());
+        self.check_access(&entry, WRITE)?;
+        if data.is_empty() {
+            return Ok(());
         }
 
-        let new_data: FileData;
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or(FileError::FileTooLarge)?;
+        self.write_content_at(&entry, offset, data)?;
+        self.set_file_size(abs_path, &entry, entry.size.max(end))
+    }
 
-        {
-            let src_entry = src_block
-                .get_entry(&src_name.into())
-                .ok_or(FileError::FileNotFound)?;
-            let dest_entry = dest_block
-                .get_entry(&dest_name.clone().into())
-                .ok_or(FileError::FileNotFound)?;
-
-            if src_entry.file_type != FileType::File || dest_entry.file_type != FileType::File {
-                return Err(FileError::FileIsDirectory.into());
-            }
+    /// Appends what `input_source` reads to the file at `path`.
+    fn append_input<T: Input + Debug>(&mut self, path: &str, input_source: &mut T) -> Result<()> {
+        let data = input_source.read_data()?;
+        self.append_bytes(path, &data)
+    }
 
-            let src_data = self.read_file_data(src_entry.blk_num)?;
-            let dest_data = self.read_file_data(dest_entry.blk_num)?;
+    /// Shortens or zero-extends the file at `path` to `len` bytes in place.
+    ///
+    /// Shrinking frees the blocks past the new end,
+    /// growing leaves a hole that reads as zeros and takes no blocks.
+    /// Every name of the file shows the new size.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileTooLarge` if `len` is larger than `MAX_FILE_SIZE`.
+    #[trace_log]
+    fn truncate(&mut self, path: &str, len: u64) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
+        }
+        self.check_access(&entry, WRITE)?;
 
-            new_data = (dest_data + "\n".into()) + src_data;
+        self.resize_content(&entry, len)?;
+        self.set_file_size(abs_path, &entry, len)
+    }
 
-            self.clear_file_data(dest_entry.blk_num)?;
-            self.write_data(&new_data, dest_entry.blk_num)?;
+    /// Reads up to `len` bytes of the file at `path` from `offset`,
+    /// fewer if the file ends before that.
+    ///
+    /// Only the blocks holding the range are read, no matter how large the file is.
+    #[trace_log]
+    fn read_range(&mut self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
         }
+        self.check_access(&entry, READ)?;
 
-        let dest_entry = dest_block
-            .get_entry_mut(&dest_name.clone().into())
-            .ok_or(FileError::FileNotFound)?;
-
-        // update size of the dest entry
-        dest_entry.size = new_data.get_size() as u64;
+        let data = self.read_content_range(&entry, offset, len)?;
+        self.touch_accessed(&abs_path)?;
+        Ok(data)
+    }
 
-        self.update_dir(&mut dest_block, abs_dest)?;
+    /// Prints the content of the file at `path` like `hexdump -C`.
+    #[trace_log]
+    fn hexdump(&mut self, path: &str) -> Result<()> {
+        let data = self.read_range(path, 0, u64::MAX)?;
+        self.io_handler.write(hex::hexdump(&data))
+    }
 
-        Ok(())
+    /// Prints the content of the file at `path` like `xxd`.
+    #[trace_log]
+    fn xxd(&mut self, path: &str) -> Result<()> {
+        let data = self.read_range(path, 0, u64::MAX)?;
+        self.io_handler.write(hex::xxd(&data))
     }
 }
@@ -1,32 +1,91 @@
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::Result;
 use logger_macro::trace_log;
+use serde_derive::{Deserialize, Serialize};
 
 use rustic_disk::traits::BlockStorage;
 use rustic_disk::Disk;
 
 use crate::dir_entry::{DirBlock, DirEntry, FileType};
+use crate::errors::{FSError, FileError};
 use crate::traits::Format;
-use crate::{FatType, FileSystem, FAT, FAT_BLK, ROOT_BLK, READ_WRITE_EXECUTE};
+use crate::{FatType, FileSystem, FAT, FAT_BLK, ROOT_BLK, DIR_MODE};
+
+/// The layout used for the entries of every directory on the volume.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DirIndex {
+    /// Entries fill the first free slot, lookups scan the whole directory.
+    #[default]
+    Linear,
+    /// Every block of the directory is a hash bucket keyed by the entry name,
+    /// lookups only scan the bucket the name hashes to.
+    Hashed,
+}
+
+impl fmt::Display for DirIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirIndex::Linear => write!(f, "linear"),
+            DirIndex::Hashed => write!(f, "hashed"),
+        }
+    }
+}
+
+impl FromStr for DirIndex {
+    type Err = FSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(DirIndex::Linear),
+            "hashed" => Ok(DirIndex::Hashed),
+            _ => Err(FSError::InvalidFormatOption(s.to_string())),
+        }
+    }
+}
+
+/// Options chosen when formatting the volume.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatOptions {
+    pub dir_index: DirIndex,
+}
 
 impl Format for FileSystem {
     #[trace_log]
     fn format(&mut self) -> Result<()> {
+        self.format_with(FormatOptions::default())
+    }
+
+    /// Wipes the volume and sets up an empty root directory.
+    ///
+    /// Only the superuser may format, the user database starts over with just `root`,
+    /// which is locked until it gets a password.
+    #[trace_log]
+    fn format_with(&mut self, options: FormatOptions) -> Result<()> {
+        if !self.credentials.is_root() {
+            return Err(FileError::NotPermitted("format".to_string()).into());
+        }
+
         // disk should always exist since we handle making a dsk in the constructor
         if Disk::disk_exists() {
             self.disk.delete_disk()?;
         }
 
-        let mut fat = FAT::new();
+        let mut fat = FAT::with_options(options);
 
         let blk = DirBlock {
             path: "/".to_string(),
             parent_entry: DirEntry {
                 name: "/".into(),
                 file_type: FileType::Directory,
-                access_level: READ_WRITE_EXECUTE,
+                mode: DIR_MODE,
                 ..Default::default()
             },
             blk_num: 0,
+            blocks: vec![ROOT_BLK as u16],
+            index: options.dir_index,
+            bucket: None,
             entries: vec![DirEntry::default(); Self::num_entries()],
         };
 
@@ -41,3 +100,11 @@ impl Format for FileSystem {
         Ok(())
     }
 }
+
+impl FileSystem {
+    /// Tells whether the volume has been formatted,
+    /// a new disk has no block reserved for the root directory until then.
+    pub fn is_formatted(&self) -> bool {
+        self.fat.get(ROOT_BLK as usize) != Some(&FatType::Free)
+    }
+}
@@ -0,0 +1,325 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use anyhow::Result;
+
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::FileError;
+use crate::traits::File;
+use crate::{FileSystem, MAX_FILE_SIZE, READ, WRITE};
+
+/// How `FileSystem::open` opens a file, set up like `std::fs::OpenOptions`.
+///
+/// ```
+/// # use file_system::prelude::OpenOptions;
+/// let options = OpenOptions::new().write(true).create(true).truncate(true);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenOptions {
+    /// Options with every flag unset, at least one of read, write or append has to be set.
+    pub fn new() -> Self {
+        OpenOptions::default()
+    }
+
+    pub fn read(mut self, read: bool) -> Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Every write goes to the end of the file, no matter where the handle was seeked to.
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Empties the file when it is opened.
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Creates the file if it doesn't exist.
+    pub fn create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Creates the file and fails if it already exists.
+    pub fn create_new(mut self, create_new: bool) -> Self {
+        self.create_new = create_new;
+        self
+    }
+
+    fn writes(&self) -> bool {
+        self.write || self.append
+    }
+
+    /// Checks that the flags make sense together.
+    ///
+    /// # Errors
+    /// Returns `FileError::InvalidOpenOptions` if the file would be neither read nor written,
+    /// or if it would be created or truncated without being written.
+    fn check(&self) -> Result<()> {
+        if !self.read && !self.writes() {
+            return Err(FileError::InvalidOpenOptions("neither read nor write".into()).into());
+        }
+        if (self.create || self.create_new || self.truncate) && !self.writes() {
+            return Err(
+                FileError::InvalidOpenOptions("create and truncate need write".into()).into(),
+            );
+        }
+        if self.truncate && self.append {
+            return Err(
+                FileError::InvalidOpenOptions("truncate and append together".into()).into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// An open file of a `FileSystem`, read and written through `std::io::Read`, `Write` and `Seek`.
+///
+/// Reads only fetch the blocks they need. The content is loaded on the first change
+/// and written back by `flush`, which also happens when the handle is dropped.
+/// Errors while dropping are ignored, call `flush` to see them.
+#[derive(Debug)]
+pub struct FileHandle<'a> {
+    fs: &'a mut FileSystem,
+    path: String,
+    options: OpenOptions,
+    entry: DirEntry,
+    /// The whole content once it has been changed, `None` while it is only read.
+    buffer: Option<Vec<u8>>,
+    pos: u64,
+    dirty: bool,
+}
+
+impl FileHandle<'_> {
+    /// The absolute path of the open file, symbolic links resolved.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The length of the content in bytes, including unflushed writes.
+    pub fn len(&self) -> u64 {
+        match &self.buffer {
+            Some(buffer) => buffer.len() as u64,
+            None => self.entry.size,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The offset the next read or write starts at.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Truncates or zero-extends the file to `size` bytes, the position stays where it is.
+    ///
+    /// Without unflushed writes the file is resized in place, otherwise the change is buffered.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotOpenFor` if the handle wasn't opened for writing
+    /// and `FileError::FileTooLarge` if `size` is larger than `MAX_FILE_SIZE`.
+    pub fn set_len(&mut self, size: u64) -> Result<()> {
+        if size > MAX_FILE_SIZE {
+            return Err(FileError::FileTooLarge.into());
+        }
+        if self.buffer.is_none() && self.options.writes() {
+            self.fs.truncate(&self.path, size)?;
+            self.entry.size = size;
+            return Ok(());
+        }
+        self.buffer()?.resize(size as usize, 0);
+        Ok(())
+    }
+
+    /// Writes the content back to the file if it changed since the last flush.
+    pub fn sync(&mut self) -> Result<()> {
+        if let (true, Some(buffer)) = (self.dirty, &self.buffer) {
+            self.entry.blk_num = self.fs.replace_file_data(&self.path, buffer)?;
+            self.entry.size = buffer.len() as u64;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Returns the content to be changed, loading it on the first change.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotOpenFor` if the handle wasn't opened for writing.
+    fn buffer(&mut self) -> Result<&mut Vec<u8>> {
+        if !self.options.writes() {
+            return Err(FileError::NotOpenFor("writing".into()).into());
+        }
+        if self.buffer.is_none() {
+            self.buffer = Some(self.fs.read_content(&self.entry)?);
+        }
+        self.dirty = true;
+        Ok(self.buffer.get_or_insert_with(Vec::new))
+    }
+}
+
+/// Wraps an error of the file system for the `std::io` traits.
+fn io_error(e: anyhow::Error) -> io::Error {
+    match e.downcast::<FileError>() {
+        Ok(e @ FileError::NotOpenFor(_)) => io::Error::new(io::ErrorKind::PermissionDenied, e),
+        Ok(e @ FileError::FileTooLarge) => io::Error::new(io::ErrorKind::FileTooLarge, e),
+        Ok(e) => io::Error::other(e),
+        Err(e) => io::Error::other(e.to_string()),
+    }
+}
+
+impl Read for FileHandle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.options.read {
+            return Err(io_error(FileError::NotOpenFor("reading".into()).into()));
+        }
+        let n = match &self.buffer {
+            Some(buffer) => {
+                let start = (self.pos as usize).min(buffer.len());
+                let n = buf.len().min(buffer.len() - start);
+                buf[..n].copy_from_slice(&buffer[start..start + n]);
+                n
+            }
+            None => {
+                let data = self
+                    .fs
+                    .read_content_range(&self.entry, self.pos, buf.len() as u64)
+                    .map_err(io_error)?;
+                buf[..data.len()].copy_from_slice(&data);
+                data.len()
+            }
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for FileHandle<'_> {
+    /// Writes `buf` at the position, a position past the end fills the gap with zeros.
+    ///
+    /// Fails with `io::ErrorKind::FileTooLarge` if the file would grow past `MAX_FILE_SIZE`.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = if self.options.append {
+            self.len()
+        } else {
+            self.pos
+        };
+        let end = match start.checked_add(buf.len() as u64) {
+            Some(end) if end <= MAX_FILE_SIZE => end,
+            _ => return Err(io_error(FileError::FileTooLarge.into())),
+        };
+
+        let buffer = self.buffer().map_err(io_error)?;
+        let (start, end) = (start as usize, end as usize);
+        if end > buffer.len() {
+            buffer.resize(end, 0);
+        }
+        buffer[start..end].copy_from_slice(buf);
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sync().map_err(io_error)
+    }
+}
+
+impl Seek for FileHandle<'_> {
+    /// Moves the position, seeking past the end is allowed and only grows the file once written to.
+    ///
+    /// Positions past `MAX_FILE_SIZE` can't be reached.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) if pos <= MAX_FILE_SIZE => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative position or past the largest file size",
+            )),
+        }
+    }
+}
+
+impl Drop for FileHandle<'_> {
+    fn drop(&mut self) {
+        let _ = self.sync();
+    }
+}
+
+impl FileSystem {
+    /// Opens the file at `path` as a handle for `std::io` style access.
+    ///
+    /// Reading needs read permission on the file and writing write permission,
+    /// creating it needs write permission on its directory.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileNotFound` if the file doesn't exist and isn't to be created,
+    /// `FileError::FileAlreadyExists` if it exists and `create_new` is set
+    /// and `FileError::FileIsDirectory` if the path names a directory.
+    pub fn open(&mut self, path: &str, options: OpenOptions) -> Result<FileHandle<'_>> {
+        options.check()?;
+        let abs_path = self.resolve_path(path, true)?;
+
+        let entry = match self.lookup_entry(&abs_path, false) {
+            Ok(_) if options.create_new => return Err(FileError::FileAlreadyExists.into()),
+            Ok(entry) => entry,
+            Err(e)
+                if matches!(e.downcast_ref(), Some(FileError::FileNotFound))
+                    && (options.create || options.create_new) =>
+            {
+                self.create_file_with_content(&abs_path, "")?;
+                self.lookup_entry(&abs_path, false)?
+            }
+            Err(e) => return Err(e),
+        };
+        if entry.file_type != FileType::File {
+            return Err(FileError::FileIsDirectory.into());
+        }
+
+        let required = match (options.read, options.writes()) {
+            (true, true) => READ | WRITE,
+            (true, false) => READ,
+            _ => WRITE,
+        };
+        self.check_access(&entry, required)?;
+
+        if options.read {
+            self.touch_accessed(&abs_path)?;
+        }
+
+        Ok(FileHandle {
+            fs: self,
+            path: abs_path,
+            options,
+            entry,
+            buffer: options.truncate.then(Vec::new),
+            pos: 0,
+            dirty: options.truncate,
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+#[cfg(feature = "py-bindings")]
+use pyo3::pyclass;
+
+use crate::dir_entry::{DirEntry, FileType};
+use crate::permissions::Permissions;
+use crate::FileSystem;
+
+/// What the file system tells about an entry, for callers that want data instead of printed text.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "py-bindings", pyclass(get_all))]
+pub struct EntryInfo {
+    pub name: String,
+    pub file_type: FileType,
+    pub size: u64,
+    /// Blocks allocated to the data, fewer than the size needs if the file has holes.
+    pub blocks: u64,
+    pub link_count: u16,
+    pub blk_num: u16,
+    /// Permission bits `rwxrwxrwx` for the owner, the group and everyone else.
+    pub mode: u16,
+    pub uid: u16,
+    pub gid: u16,
+    /// Creation, modification and access times in seconds since the Unix epoch, `0` if unknown.
+    pub created: i64,
+    pub modified: i64,
+    pub accessed: i64,
+    /// The target of a symbolic link, `None` for every other entry.
+    pub target: Option<String>,
+}
+
+impl EntryInfo {
+    /// Returns the mode of the entry as typed permissions.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_bits_truncate(self.mode)
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type == FileType::Symlink
+    }
+}
+
+impl FileSystem {
+    /// Describes `entry`, reading the target if it is a symbolic link.
+    pub(crate) fn entry_info(&self, entry: &DirEntry) -> Result<EntryInfo> {
+        let target = match entry.file_type {
+            FileType::Symlink => Some(self.read_link_target(entry)?),
+            _ => None,
+        };
+        let blocks = self.count_chain_blocks(entry.blk_num)?;
+        Ok(describe_entry(entry, target, blocks))
+    }
+
+    /// Describes `entry` for a directory listing, which goes on past entries that can't be read.
+    ///
+    /// A symbolic link whose target can't be read is listed without it
+    /// and an entry whose chain can't be followed counts no blocks.
+    pub(crate) fn listed_entry_info(&self, entry: &DirEntry) -> EntryInfo {
+        let target = match entry.file_type {
+            FileType::Symlink => self.read_link_target(entry).ok(),
+            _ => None,
+        };
+        let blocks = self.count_chain_blocks(entry.blk_num).unwrap_or_default();
+        describe_entry(entry, target, blocks)
+    }
+}
+
+fn describe_entry(entry: &DirEntry, target: Option<String>, blocks: u64) -> EntryInfo {
+    EntryInfo {
+        name: entry.name.to_string(),
+        file_type: entry.file_type,
+        size: entry.size,
+        blocks,
+        link_count: entry.link_count,
+        blk_num: entry.blk_num,
+        mode: entry.mode,
+        uid: entry.uid,
+        gid: entry.gid,
+        created: entry.created,
+        modified: entry.modified,
+        accessed: entry.accessed,
+        target,
+    }
+}
@@ -15,8 +15,13 @@ use rustic_disk::Disk;
 use crate::dir_entry::{DirBlock, DirEntry, FileType};
 use crate::errors::{FSError, IOHandlerError};
 use crate::fat::{FatType, FAT};
-use crate::prelude::{File, IOHandler};
+use crate::prelude::IOHandler;
+use crate::credentials::Credentials;
+use crate::times::AtimePolicy;
 
+mod acl;
+mod content;
+mod credentials;
 mod dir_entry;
 mod directories;
 mod errors;
@@ -24,13 +29,23 @@ mod fat;
 mod file_data;
 mod files;
 mod format;
+mod handle;
+mod info;
+mod links;
+mod migrate;
 mod other;
+mod permissions;
 pub mod prelude;
 #[cfg(feature = "py-bindings")]
 mod py_bindings;
 mod tests;
+mod times;
 mod traits;
+mod usage;
+mod users;
+mod xattr;
 mod utils;
+mod walk;
 mod execute_py;
 
 #[cfg(feature = "py-bindings")]
@@ -100,18 +115,18 @@ const FAT_BLK: u64 = 1;
 /// * `disk`: A `Disk` object representing the disk on which the file system is stored.
 ///
 /// * `curr_block`: A `DirBlock`
-/// object representing the current directory block that the file system is interacting with.
+///   object representing the current directory block that the file system is interacting with.
 /// * `fat`: A `FAT` object representing the File Allocation Table of the file system.
 ///
 /// * `io_handler`: A boxed dynamic `IOHandler` trait object.
-/// This is used for handling input and output operations in the file system.
+///   This is used for handling input and output operations in the file system.
 ///   The `IOHandler` trait requires an `Input` associated type and an `Output` associated type,
-/// both of which are `String` in this case.
+///   both of which are `String` in this case.
 ///   The `IOHandler` trait also requires the implementation of two methods: `read` and `write`.
 ///   The `read` method reads input from the user and returns a `Result<String>`.
 ///   The `write` method takes a `String` as input and writes it as output, returning a `Result<()>`.
 ///   The `IOHandler` trait object is also required to be both `Send`
-/// and `Sync`, allowing it to be safely shared across threads.
+///   and `Sync`, allowing it to be safely shared across threads.
 ///
 /// ## Example with `StdIOHandler`
 ///
@@ -132,6 +147,8 @@ pub struct FileSystem {
     disk: Disk,
     curr_block: DirBlock,
     fat: FAT,
+    atime_policy: AtimePolicy,
+    credentials: Credentials,
     pub io_handler: Box<dyn IOHandler<Input = String, Output = String> + Send + Sync>,
 }
 
@@ -144,6 +161,8 @@ impl Clone for FileSystem {
             disk: self.disk.clone(),
             curr_block: self.curr_block.clone(),
             fat: self.fat.clone(),
+            atime_policy: self.atime_policy,
+            credentials: self.credentials,
             io_handler: self.io_handler.clone_box(),
         }
     }
@@ -152,30 +171,21 @@ impl Clone for FileSystem {
 const READ: u8 = 0x04;
 const WRITE: u8 = 0x02;
 const EXECUTE: u8 = 0x01;
-const READ_WRITE: u8 = READ | WRITE;
-const READ_EXECUTE: u8 = READ | EXECUTE;
-const WRITE_EXECUTE: u8 = WRITE | EXECUTE;
-const READ_WRITE_EXECUTE: u8 = READ | WRITE | EXECUTE;
-const NONE: u8 = 0x00;
-
-#[trace_log]
-fn get_access_rights(access: u8) -> String {
-    match access {
-        READ_WRITE_EXECUTE => "rwx".to_string(),
-        READ_WRITE => "rw-".to_string(),
-        READ_EXECUTE => "r-x".to_string(),
-        READ => "r--".to_string(),
-        WRITE_EXECUTE => "-wx".to_string(),
-        WRITE => "-w-".to_string(),
-        EXECUTE => "--x".to_string(),
-        NONE => "---".to_string(),
-        _ => "???".to_string(),
-    }
-}
+
+/// Default mode of new files, `rw-r--r--`.
+const FILE_MODE: u16 = 0o644;
+/// Default mode of new directories, `rwxr-xr-x`.
+const DIR_MODE: u16 = 0o755;
+/// Mode of symbolic links, access is decided by their target.
+const SYMLINK_MODE: u16 = 0o777;
+
+/// Largest size of a file in bytes, the capacity of the volume.
+pub const MAX_FILE_SIZE: u64 = Disk::DISK_SIZE as u64;
 
 impl FileSystem {
+    /// The number of directory slots in one block, leaving room for the length prefix of the chunk.
     pub fn num_entries() -> usize {
-        Disk::BLOCK_SIZE / DirEntry::calculate_max_size()
+        (Disk::BLOCK_SIZE - size_of::<u64>()) / DirEntry::calculate_max_size()
     }
 
     /// Creates a new `FileSystem` object.
@@ -215,7 +225,7 @@ impl FileSystem {
             debug!("Creating new file system");
             debug!("Max entries per block: {}", Self::num_entries());
         }
-        let (curr_block, fat, disk) = if !Disk::disk_exists() {
+        let (curr_block, fat, disk, migrate) = if !Disk::disk_exists() {
             #[cfg(target_arch = "wasm32")]
             let mut disk = Disk::new()?;
             #[cfg(not(target_arch = "wasm32"))]
@@ -226,23 +236,38 @@ impl FileSystem {
                 parent_entry: DirEntry {
                     name: "/".into(),
                     file_type: FileType::Directory,
-                    access_level: READ_WRITE_EXECUTE,
+                    mode: DIR_MODE,
                     ..Default::default()
                 },
                 blk_num: 0,
+                blocks: vec![0],
+                index: fat.options.dir_index,
+                bucket: None,
                 entries: vec![DirEntry::default(); Self::num_entries()],
             };
             disk.write_block(0, &root_block)?;
             disk.write_block(1, &fat)?;
-            (root_block, fat, disk)
+            (root_block, fat, disk, false)
         } else {
             let disk = Disk::new()?;
-            let mut root_block: DirBlock = disk.read_block(0)?;
-            root_block.parent_entry.file_type = FileType::Directory;
-            root_block.parent_entry.access_level = READ_WRITE_EXECUTE;
-            root_block.parent_entry.name = "/".into();
             let fat: FAT = disk.read_block(1)?;
-            (root_block, fat, disk)
+            let root_entry = DirEntry {
+                name: "/".into(),
+                file_type: FileType::Directory,
+                mode: DIR_MODE,
+                ..Default::default()
+            };
+            // volumes in an older layout are read once they are migrated
+            let migrate = fat.version != fat::FAT_VERSION;
+            let root_block = if migrate {
+                DirBlock {
+                    parent_entry: root_entry,
+                    ..Default::default()
+                }
+            } else {
+                Self::read_dir_chain(&disk, &fat, &root_entry)?
+            };
+            (root_block, fat, disk, migrate)
         };
 
         #[cfg(feature = "debug")]
@@ -251,12 +276,22 @@ impl FileSystem {
             trace!("FAT: {:?}", fat);
         }
 
-        Ok(FileSystem {
+        let mut fs = FileSystem {
             disk,
             curr_block,
             fat,
+            atime_policy: AtimePolicy::default(),
+            credentials: Credentials::default(),
             io_handler,
-        })
+        };
+
+        if migrate {
+            fs.migrate()?;
+            let root_entry = fs.curr_block.parent_entry.clone();
+            fs.curr_block = Self::read_dir_chain(&fs.disk, &fs.fat, &root_entry)?;
+        }
+
+        Ok(fs)
     }
 
     /// Updates the current directory block of the file system.
@@ -266,35 +301,32 @@ impl FileSystem {
     /// It returns a `Result<()>` indicating the success or failure of the operation.
     #[trace_log]
     pub fn update_curr_dir(&mut self) -> Result<()> {
+        let path = self.curr_block.path.clone();
         self.curr_block = self.read_dir_block(&self.curr_block.parent_entry)?;
+        self.curr_block.path = path;
         Ok(())
     }
 
     /// Writes the current directory block to the disk.
     ///
     /// This method is only available when the target architecture is not `wasm32`.
-    /// It writes the current directory block to the disk at the block number of the current directory block.
+    /// It writes every block of the current directory to the disk.
     /// It returns a `Result<()>` indicating the success or failure of the operation.
     #[trace_log]
     #[cfg(not(target_arch = "wasm32"))]
     pub fn write_curr_blk(&self) -> Result<()> {
-        let block_to_write = self.curr_block.blk_num;
-        self.disk
-            .write_block(block_to_write as usize, &self.curr_block)?;
-        Ok(())
+        self.write_dir_block(&self.curr_block)
     }
 
     /// Writes the current directory block to the disk.
     ///
     /// This method is only available when the target architecture is `wasm32`.
-    /// It writes the current directory block to the disk at the block number of the current directory block.
+    /// It writes every block of the current directory to the disk.
     /// It returns a `Result<()>` indicating the success or failure of the operation.
     #[cfg(target_arch = "wasm32")]
     pub fn write_curr_blk(&mut self) -> Result<()> {
-        let block_to_write = self.curr_block.blk_num;
-        self.disk
-            .write_block(block_to_write as usize, &self.curr_block)?;
-        Ok(())
+        let block = self.curr_block.clone();
+        self.write_dir_block(&block)
     }
 
     /// Returns the block number of the first free block in the file allocation table (FAT).
@@ -340,7 +372,7 @@ impl FileSystem {
     ///
     /// # Arguments
     /// * `data: &T where T: Serialize + Debug` -
-    /// The data to write to the disk. This data is serialized before being written.
+    ///   The data to write to the disk. This data is serialized before being written.
     /// * `start_blk: u16` - The block number to start writing the data at.
     ///
     /// # Errors
@@ -388,6 +420,10 @@ impl FileSystem {
 
 
     pub fn set_fat_block(&mut self, blk: u16, new_val: FatType) -> Result<()> {
+        // only a link to a following block can skip a hole
+        if !matches!(new_val, FatType::Taken(_)) {
+            self.fat.set_holes_after(blk, 0);
+        }
         self.fat[blk as usize] = new_val;
         Ok(())
     }
@@ -518,16 +554,18 @@ impl FileSystem {
                         // Instead of reading, we write zeroes to the block
                         self.disk.write_raw_data(*blk_num as usize, &zero_data)?;
 
-                        let lol: usize = blk_num.clone() as usize;
+                        let lol: usize = *blk_num as usize;
                         self.fat[lol] = FatType::Free;
+                        self.fat.set_holes_after(*blk_num, 0);
                         self.disk.write_block(FAT_BLK as usize, &self.fat)?;
                         *blk_num = next_blk;
                     }
                     Some(&FatType::EOF) => {
                         // Clear the EOF block as well
                         self.disk.write_raw_data(*blk_num as usize, &zero_data)?;
-                        let lol: usize = blk_num.clone() as usize;
+                        let lol: usize = *blk_num as usize;
                         self.fat[lol] = FatType::Free;
+                        self.fat.set_holes_after(*blk_num, 0);
                         self.disk.write_block(FAT_BLK as usize, &self.fat)?;
                         break;
                     }
@@ -543,24 +581,45 @@ impl FileSystem {
         Ok(())
     }
 
+    /// Runs `f` and puts the FAT back as it was if it fails,
+    /// zeroing the blocks taken in between so that nothing of the attempt is left on the disk.
+    ///
+    /// Only blocks `f` takes are undone, so it must not free blocks or rewrite existing ones
+    /// before its last step that can fail.
+    pub(crate) fn all_or_nothing<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let fat = self.fat.clone();
+        let result = f(self);
+        if result.is_err() {
+            let zero_data = vec![0u8; Disk::BLOCK_SIZE];
+            for blk in 0..fat.len() {
+                if fat[blk] == FatType::Free && self.fat[blk] != FatType::Free {
+                    self.disk.write_raw_data(blk, &zero_data)?;
+                }
+            }
+            self.fat = fat;
+            self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+        }
+        result
+    }
+
     /// Removes the directory data for a given directory entry.
     ///
     /// This method takes a directory entry and a path as input
     /// and removes all the entries in the directory following the File Allocation Table
     /// (FAT).
     /// It reads the directory block of the given directory entry and iterates over all the entries in the directory block.
-    /// For each entry, if the entry is a file, it deletes the file.
+    /// For each entry, if the entry is a file, it clears the data of the file.
     /// If the entry is a directory, it recursively removes the directory data for the entry.
     /// After iterating over all the entries,
-    /// it writes zeroes to the block of the given directory entry
-    /// and updates the FAT entry for the block to `Free`.
+    /// it writes zeroes to every block of the given directory
+    /// and updates the FAT entries for the blocks to `Free`.
     ///
     /// # Arguments
     /// * `dir_entry: &DirEntry` - The directory entry to remove the directory data for.
     /// * `path: &str` - The path of the directory.
     ///
     /// # Errors
-    /// Returns an error if deleting a file, removing directory data,
+    /// Returns an error if clearing file data, removing directory data,
     /// reading a directory block, writing zeroes to a block, or updating the FAT fails.
     #[trace_log]
     pub fn remove_dir_data(&mut self, dir_entry: &DirEntry, path: &str) -> Result<()> {
@@ -574,17 +633,47 @@ impl FileSystem {
             let new_path = format!("{}/{}", path, entry.name);
 
             match entry.file_type {
-                FileType::File => self.delete_file(&new_path)?,
+                FileType::File | FileType::Symlink if entry.link_count > 1 => {
+                    // an earlier entry may have been another name of the same file
+                    let current = self.read_dir_block(dir_entry)?;
+                    let entry = current.get_entry(&entry.name).unwrap_or(entry).clone();
+                    self.release_data(&entry)?
+                }
+                FileType::File | FileType::Symlink => {
+                    self.clear_file_data(entry.blk_num)?;
+                    self.release_side_blocks(entry)?
+                }
                 FileType::Directory => self.remove_dir_data(entry, &new_path)?,
             }
         }
 
-        let zero_data = vec![0u8; Disk::BLOCK_SIZE];
-        self.disk
-            .write_raw_data(dir_entry.blk_num as usize, &zero_data)?;
+        self.clear_file_data(dir_entry.blk_num)?;
+        self.release_side_blocks(dir_entry)
+    }
 
-        self.fat[dir_entry.blk_num as usize] = FatType::Free;
-        self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+    /// Checks that every directory in the tree of `dir_entry` may be emptied before anything is freed,
+    /// listing a directory takes read access and removing its entries write and search access.
+    ///
+    /// # Errors
+    /// Returns the permission error of the first directory that can't be emptied.
+    #[trace_log]
+    pub(crate) fn check_removable(&self, dir_entry: &DirEntry) -> Result<()> {
+        let block = self.read_dir_block(dir_entry)?;
+        let mut entries = block
+            .entries
+            .iter()
+            .filter(|entry| !entry.name.is_empty())
+            .peekable();
+        if entries.peek().is_none() {
+            return Ok(());
+        }
+
+        self.check_access(dir_entry, READ | WRITE | EXECUTE)?;
+        for entry in entries {
+            if entry.file_type == FileType::Directory {
+                self.check_removable(entry)?;
+            }
+        }
         Ok(())
     }
 
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use logger_macro::trace_log;
+
+use crate::acl::NO_ACL;
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::FileError;
+use crate::file_data::FileData;
+use crate::times::now;
+use crate::traits::Links;
+use crate::utils::path_handler::{absolutize_from, anchor, split_path};
+use crate::utils::check_name;
+use crate::xattr::NO_XATTRS;
+use crate::{FileSystem, SYMLINK_MODE, WRITE};
+
+/// The most symbolic links followed while resolving a single path,
+/// more than this is treated as a loop.
+pub const MAX_SYMLINKS: usize = 40;
+
+impl FileSystem {
+    /// Reads the target path stored in a symbolic link.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotASymlink` if the entry is not a symbolic link.
+    #[trace_log]
+    pub(crate) fn read_link_target(&self, entry: &DirEntry) -> Result<String> {
+        if entry.file_type != FileType::Symlink {
+            return Err(FileError::NotASymlink(entry.name.to_string()).into());
+        }
+
+        let data = self.read_file_data(entry.blk_num)?;
+        Ok(String::from_utf8(data.data)?)
+    }
+
+    /// Resolves every symbolic link in `path` and returns the absolute path it refers to.
+    ///
+    /// Links in the last component are only followed if `follow_last` is set,
+    /// so that operations on the link itself can still find it.
+    /// The last component doesn't have to exist.
+    ///
+    /// # Errors
+    /// Returns `FileError::TooManySymlinks` if more than `MAX_SYMLINKS` links are followed,
+    /// `FileError::FileNotFound` if a directory on the way is missing
+    /// and `FileError::NotADirectory` if one of them is a file.
+    #[trace_log]
+    pub fn resolve_path(&self, path: &str, follow_last: bool) -> Result<String> {
+        let abs_path = absolutize_from(path, &self.curr_block.path);
+
+        // components left to resolve, the next one on top
+        let mut pending = abs_path
+            .split('/')
+            .rev()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut resolved: Vec<String> = Vec::new();
+        let mut dir = Self::root_entry();
+        let mut links = 0;
+
+        while let Some(name) = pending.pop() {
+            match name.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    resolved.pop();
+                    dir = self.dir_entry_at(&resolved.join("/"))?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let is_last = pending.iter().all(|name| name.is_empty() || name == ".");
+            self.check_search(&dir)?;
+            let bucket = self.read_dir_bucket(&dir, &name.clone().into())?;
+            let entry = match bucket.get_entry(&name.clone().into()) {
+                Some(entry) => entry.clone(),
+                None if is_last => {
+                    resolved.push(name);
+                    break;
+                }
+                None => return Err(FileError::FileNotFound.into()),
+            };
+
+            match entry.file_type {
+                FileType::Symlink if follow_last || !is_last => {
+                    links += 1;
+                    if links > MAX_SYMLINKS {
+                        return Err(FileError::TooManySymlinks(path.to_string()).into());
+                    }
+
+                    let target = self.read_link_target(&entry)?;
+                    if target.starts_with('/') {
+                        resolved.clear();
+                        dir = Self::root_entry();
+                    }
+                    pending.extend(target.split('/').rev().map(str::to_string));
+                }
+                FileType::Directory if !is_last => {
+                    dir = entry;
+                    resolved.push(name);
+                }
+                _ if is_last => resolved.push(name),
+                _ => return Err(FileError::NotADirectory(name.into()).into()),
+            }
+        }
+
+        Ok(format!("/{}", resolved.join("/")))
+    }
+
+    /// Applies `update` to every entry sharing the data chain at `blk_num`
+    /// and writes back the directories holding them.
+    ///
+    /// Returns the path of the parent directory of each updated entry.
+    pub(crate) fn update_links(
+        &mut self,
+        blk_num: u16,
+        update: impl Fn(&mut DirEntry),
+    ) -> Result<Vec<String>> {
+        let mut parents = Vec::new();
+        let mut pending = vec![self.read_root_dir()?];
+
+        while let Some(mut block) = pending.pop() {
+            let mut changed = false;
+            for entry in block.entries.iter_mut() {
+                if !entry.name.is_empty()
+                    && entry.file_type != FileType::Directory
+                    && entry.blk_num == blk_num
+                {
+                    update(entry);
+                    parents.push(block.path.clone());
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_dir_block(&block)?;
+            }
+
+            for entry in block.iter() {
+                if entry.file_type == FileType::Directory {
+                    let mut child = self.read_dir_block(entry)?;
+                    child.path = format!("{}/{}", block.path.trim_end_matches('/'), entry.name);
+                    pending.push(child);
+                }
+            }
+        }
+
+        self.update_curr_dir()?;
+        Ok(parents)
+    }
+
+    /// Applies `update` to the entry at the root-relative `path` and to every other name of the same file.
+    ///
+    /// # Errors
+    /// Returns `FileError::NotPermitted` for the root, it has no stored entry to change.
+    pub(crate) fn update_entry_at(
+        &mut self,
+        path: &str,
+        update: impl Fn(&mut DirEntry),
+    ) -> Result<()> {
+        let (parent, name) = split_path(self.resolve_path(&anchor(path), false)?);
+        if name.is_empty() {
+            return Err(FileError::NotPermitted("/".into()).into());
+        }
+
+        let mut parent_block = self.traverse_bucket(parent, &name)?;
+        let entry = parent_block
+            .get_entry_mut(&name.into())
+            .ok_or(FileError::FileNotFound)?;
+
+        if entry.file_type != FileType::Directory && entry.link_count > 1 {
+            let blk_num = entry.blk_num;
+            self.update_links(blk_num, update)?;
+            return Ok(());
+        }
+
+        update(entry);
+        self.write_dir_block(&parent_block)?;
+        self.update_curr_dir()
+    }
+
+    /// Drops one link to the data of a file whose entry was removed,
+    /// the data is only cleared once the last link is gone.
+    #[trace_log]
+    pub(crate) fn release_data(&mut self, entry: &DirEntry) -> Result<()> {
+        if entry.link_count > 1 {
+            self.update_links(entry.blk_num, |link| {
+                link.link_count = link.link_count.saturating_sub(1)
+            })?;
+            Ok(())
+        } else {
+            self.clear_file_data(entry.blk_num)?;
+            self.release_side_blocks(entry)
+        }
+    }
+}
+
+impl Links for FileSystem {
+    /// Creates a symbolic link at `path` pointing to `target`.
+    ///
+    /// The target is stored as given and doesn't have to exist,
+    /// relative targets are resolved from the directory holding the link.
+    #[trace_log]
+    fn create_symlink(&mut self, target: &str, path: &str) -> Result<()> {
+        let abs_path = absolutize_from(path, &self.curr_block.path);
+        let (parent, name) = split_path(abs_path.clone());
+
+        check_name(&name)?;
+        if target.is_empty() {
+            return Err(FileError::InvalidFilename(target.to_string()).into());
+        }
+
+        let mut parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        self.check_access(&parent_block.parent_entry, WRITE)?;
+        if parent_block.get_entry(&name.clone().into()).is_some() {
+            return Err(FileError::FileAlreadyExists.into());
+        }
+
+        let blk_num = self.get_free_block()?;
+        self.write_data(&FileData::from(target.to_string()), blk_num)?;
+
+        let now = now();
+        let entry = DirEntry {
+            name: name.into(),
+            file_type: FileType::Symlink,
+            size: target.len() as u64,
+            blk_num,
+            mode: SYMLINK_MODE,
+            uid: self.credentials.uid,
+            gid: self.credentials.gid,
+            link_count: 1,
+            created: now,
+            modified: now,
+            accessed: now,
+            acl_blk: NO_ACL,
+            xattr_blk: NO_XATTRS,
+        };
+
+        self.add_dir_entry(&mut parent_block, entry)?;
+        self.update_dir(&mut parent_block, abs_path)?;
+        self.touch_modified(&parent)?;
+
+        Ok(())
+    }
+
+    /// Creates a hard link at `path` sharing the data of the file at `target`.
+    ///
+    /// The link count of every name of the file is raised by one.
+    /// Links to directories are not allowed.
+    #[trace_log]
+    fn create_hard_link(&mut self, target: &str, path: &str) -> Result<()> {
+        let abs_target = absolutize_from(target, &self.curr_block.path);
+        let abs_path = absolutize_from(path, &self.curr_block.path);
+        let (target_parent, target_name) = split_path(abs_target);
+        let (parent, name) = split_path(abs_path.clone());
+
+        check_name(&name)?;
+
+        let target_block = self.traverse_bucket(target_parent, &target_name)?;
+        let mut entry = target_block
+            .get_entry(&target_name.into())
+            .ok_or(FileError::FileNotFound)?
+            .clone();
+        if entry.file_type == FileType::Directory {
+            return Err(FileError::FileIsDirectory.into());
+        }
+
+        let parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        self.check_access(&parent_block.parent_entry, WRITE)?;
+        if parent_block.get_entry(&name.clone().into()).is_some() {
+            return Err(FileError::FileAlreadyExists.into());
+        }
+
+        let link_count = entry.link_count + 1;
+        self.update_links(entry.blk_num, |link| link.link_count = link_count)?;
+
+        // the parent may hold the target as well, so it is read again
+        let mut parent_block = self.traverse_bucket(parent.clone(), &name)?;
+        entry.name = name.into();
+        entry.link_count = link_count;
+        self.add_dir_entry(&mut parent_block, entry)?;
+        self.update_dir(&mut parent_block, abs_path)?;
+        self.touch_modified(&parent)?;
+
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    #[trace_log]
+    fn read_link(&self, path: &str) -> Result<String> {
+        let abs_path = self.resolve_path(path, false)?;
+        let (parent, name) = split_path(abs_path);
+
+        let parent_block = self.traverse_bucket(parent, &name)?;
+        let entry = parent_block
+            .get_entry(&name.into())
+            .ok_or(FileError::FileNotFound)?;
+
+        self.read_link_target(entry)
+    }
+
+    /// Prints the target of the symbolic link at `path`.
+    #[trace_log]
+    fn readlink(&mut self, path: &str) -> Result<()> {
+        let target = self.read_link(path)?;
+        self.io_handler.write(target)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use anyhow::Result;
+use logger_macro::trace_log;
+use rustic_disk::traits::BlockStorage;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+use crate::dir_entry::{DirBlock, DirEntry, FileType};
+use crate::errors::FSError;
+use crate::fat::{FatType, FAT_VERSION, RAW_CONTENT_VERSION};
+use crate::utils::fixed_str::FixedString;
+use crate::{FileSystem, FAT_BLK};
+
+/// Turns the single 3-bit access level of older volumes into a mode granting it to everyone,
+/// so access stays exactly as it was. Owner and group become the superuser.
+fn mode_from_access_level(access_level: u8) -> u16 {
+    let level = (access_level & 0o7) as u16;
+    level << 6 | level << 3 | level
+}
+
+/// Layout of a directory entry on volumes of version 0 and 1.
+#[derive(Debug, Deserialize)]
+struct DirEntryV1 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    access_level: u8,
+}
+
+impl From<DirEntryV1> for DirEntry {
+    fn from(entry: DirEntryV1) -> Self {
+        DirEntry {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            mode: mode_from_access_level(entry.access_level),
+            link_count: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/// Layout of a directory entry on volumes of version 2, before times were stored.
+#[derive(Debug, Deserialize)]
+struct DirEntryV2 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    access_level: u8,
+    link_count: u16,
+}
+
+impl From<DirEntryV2> for DirEntry {
+    fn from(entry: DirEntryV2) -> Self {
+        DirEntry {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            mode: mode_from_access_level(entry.access_level),
+            link_count: entry.link_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// Layout of a directory entry on volumes of version 3, before ownership was stored.
+#[derive(Debug, Deserialize)]
+struct DirEntryV3 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    access_level: u8,
+    link_count: u16,
+    created: i64,
+    modified: i64,
+    accessed: i64,
+}
+
+impl From<DirEntryV3> for DirEntry {
+    fn from(entry: DirEntryV3) -> Self {
+        DirEntry {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            mode: mode_from_access_level(entry.access_level),
+            link_count: entry.link_count,
+            created: entry.created,
+            modified: entry.modified,
+            accessed: entry.accessed,
+            ..Default::default()
+        }
+    }
+}
+
+/// Layout of a directory entry on volumes of version 4, before access control lists were stored.
+#[derive(Debug, Deserialize)]
+struct DirEntryV4 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    mode: u16,
+    uid: u16,
+    gid: u16,
+    link_count: u16,
+    created: i64,
+    modified: i64,
+    accessed: i64,
+}
+
+impl From<DirEntryV4> for DirEntry {
+    fn from(entry: DirEntryV4) -> Self {
+        DirEntry {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            mode: entry.mode,
+            uid: entry.uid,
+            gid: entry.gid,
+            link_count: entry.link_count,
+            created: entry.created,
+            modified: entry.modified,
+            accessed: entry.accessed,
+            ..Default::default()
+        }
+    }
+}
+
+/// Layout of a directory entry on volumes of version 5, before extended attributes were stored.
+#[derive(Debug, Deserialize)]
+struct DirEntryV5 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    mode: u16,
+    uid: u16,
+    gid: u16,
+    link_count: u16,
+    created: i64,
+    modified: i64,
+    accessed: i64,
+    acl_blk: u16,
+}
+
+impl From<DirEntryV5> for DirEntry {
+    fn from(entry: DirEntryV5) -> Self {
+        DirEntry {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            mode: entry.mode,
+            uid: entry.uid,
+            gid: entry.gid,
+            link_count: entry.link_count,
+            created: entry.created,
+            modified: entry.modified,
+            accessed: entry.accessed,
+            acl_blk: entry.acl_blk,
+            ..Default::default()
+        }
+    }
+}
+
+impl FileSystem {
+    /// Rewrites every directory of a volume written by an older version in the current layout.
+    ///
+    /// The entries of each directory are re-added one by one,
+    /// so directories that no longer fit their blocks grow through the FAT.
+    ///
+    /// # Errors
+    /// Returns `FSError::UnsupportedVersion` if the volume is newer than this version.
+    #[trace_log]
+    pub(crate) fn migrate(&mut self) -> Result<()> {
+        match self.fat.version {
+            0 | 1 => self.migrate_from::<DirEntryV1>()?,
+            2 => self.migrate_from::<DirEntryV2>()?,
+            3 => self.migrate_from::<DirEntryV3>()?,
+            4 => self.migrate_from::<DirEntryV4>()?,
+            5 => self.migrate_from::<DirEntryV5>()?,
+            6 | 7 => {}
+            FAT_VERSION => return Ok(()),
+            version => return Err(FSError::UnsupportedVersion(version).into()),
+        }
+        if self.fat.version < RAW_CONTENT_VERSION {
+            self.migrate_contents()?;
+        }
+
+        self.fat.version = FAT_VERSION;
+        self.disk.write_block(FAT_BLK as usize, &self.fat)?;
+        Ok(())
+    }
+
+    fn migrate_from<T>(&mut self) -> Result<()>
+    where
+        T: DeserializeOwned + Debug + Into<DirEntry>,
+    {
+        let mut pending = vec![DirEntry::new(
+            "/".into(),
+            FileType::Directory,
+            0,
+            crate::ROOT_BLK as u16,
+        )];
+
+        while let Some(dir_entry) = pending.pop() {
+            let block = self.migrate_dir::<T>(&dir_entry)?;
+            pending.extend(
+                block
+                    .iter()
+                    .filter(|entry| entry.file_type == FileType::Directory)
+                    .cloned(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Reads a directory stored with entries of type `T` and writes it back in the current layout.
+    fn migrate_dir<T>(&mut self, dir_entry: &DirEntry) -> Result<DirBlock>
+    where
+        T: DeserializeOwned + Debug + Into<DirEntry>,
+    {
+        let mut blocks = vec![dir_entry.blk_num];
+        while let Some(&FatType::Taken(next_blk)) = self.fat.get(*blocks.last().unwrap() as usize) {
+            if blocks.contains(&next_blk) {
+                return Err(FSError::InvalidBlockReference.into());
+            }
+            blocks.push(next_blk);
+        }
+
+        let mut old = DirBlock::default();
+        for blk in &blocks {
+            let chunk = self.disk.read_block::<Vec<T>>(*blk as usize)?;
+            old.entries.extend(chunk.into_iter().map(Into::into));
+        }
+        old.join_long_names();
+
+        let mut block = DirBlock {
+            parent_entry: dir_entry.clone(),
+            blk_num: dir_entry.blk_num,
+            entries: vec![DirEntry::default(); blocks.len() * Self::num_entries()],
+            blocks,
+            index: self.fat.options.dir_index,
+            ..Default::default()
+        };
+        for entry in old.iter() {
+            self.add_dir_entry(&mut block, entry.clone())?;
+        }
+        self.write_dir_block(&block)?;
+
+        Ok(block)
+    }
+
+    /// Rewrites the content of every file from a serialized `FileData` to the block-aligned format,
+    /// the size of each file becomes the length of its content.
+    fn migrate_contents(&mut self) -> Result<()> {
+        // hard links share their chain, which is only converted once
+        let mut sizes = HashMap::new();
+        let mut pending = vec![self.read_root_dir()?];
+
+        while let Some(mut block) = pending.pop() {
+            for entry in block.entries.iter_mut() {
+                if entry.name.is_empty() || entry.file_type != FileType::File {
+                    continue;
+                }
+                entry.size = match sizes.get(&entry.blk_num) {
+                    Some(&size) => size,
+                    None => {
+                        let data = self.read_file_data(entry.blk_num)?;
+                        self.clear_file_data(entry.blk_num)?;
+                        self.set_fat_block(entry.blk_num, FatType::EOF)?;
+                        self.write_content(entry.blk_num, &data.data)?;
+                        sizes.insert(entry.blk_num, data.len() as u64);
+                        data.len() as u64
+                    }
+                };
+            }
+            self.write_dir_block(&block)?;
+
+            for entry in block.iter() {
+                if entry.file_type == FileType::Directory {
+                    pending.push(self.read_dir_block(entry)?);
+                }
+            }
+        }
+
+        self.recompute_dir_sizes()?;
+        Ok(())
+    }
+}
@@ -1,194 +1,364 @@
-use crate::dir_entry::{DirBlock, FileType};
+use crate::dir_entry::{DirBlock, DirEntry, FileType};
 use anyhow::Result;
 use logger_macro::trace_log;
 
 use crate::errors::FileError;
 
-use crate::{FileSystem, READ, READ_WRITE, READ_WRITE_EXECUTE, WRITE};
-use crate::prelude::Permissions;
-use crate::utils::check_access_level;
+use crate::{FileSystem, EXECUTE, READ, WRITE};
+use crate::permissions::ModeChange;
+use crate::prelude::PermissionHandling;
+use crate::utils::check_name;
+use crate::times::now;
 use crate::traits::DirEntryHandling;
-use crate::utils::path_handler::{absolutize_from, split_path};
+use crate::utils::path_handler::{absolutize_from, anchor, split_path};
 
 impl DirEntryHandling for FileSystem {
-    /// The move function is used to move a file from one directory to another
+    /// Moves the entry at `source` to `dest`, or into `dest` if that is a directory.
+    ///
+    /// Directories are moved with everything below them, symbolic links are moved as links.
+    /// The sizes of the directories above both places are kept up to date.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileExists` if the destination is taken
+    /// and `FileError::IntoItself` if a directory would be moved into its own subtree.
     #[trace_log]
     fn move_entry(&mut self, source: &str, dest: &str) -> Result<()> {
-        let abs_src = absolutize_from(source, &self.curr_block.path);
-        let abs_dest = absolutize_from(dest, &self.curr_block.path);
+        self.move_to(source, dest, false)
+    }
 
-        let (src_parent, src_name) = split_path(abs_src.clone());
-        let (dest_parent, dest_name) = split_path(abs_dest.clone());
+    /// Moves the entry at `source` to `dest` like `move_entry`, replacing the entry the destination names.
+    ///
+    /// A file or symbolic link can only replace another one and a directory only an empty directory.
+    /// Moving an entry onto another name of the same file changes nothing.
+    ///
+    /// # Errors
+    /// Returns `FileError::FileIsDirectory` if a file would replace a directory,
+    /// `FileError::NotADirectory` if a directory would replace a file
+    /// and `FileError::DirectoryNotEmpty` if the directory to replace has entries.
+    #[trace_log]
+    fn move_entry_overwrite(&mut self, source: &str, dest: &str) -> Result<()> {
+        self.move_to(source, dest, true)
+    }
 
-        let mut src_parent_block = self.traverse_dir(src_parent)?;
-        let mut dest_parent_block = self.traverse_dir(dest_parent)?;
+    /// Copies the entry at `source` to `dest`, or into `dest` if that is a directory.
+    ///
+    /// Directories are copied with everything below them. Every copy is a new entry
+    /// with a single name and data of its own, which keeps the mode and the extended attributes
+    /// of its source and gets the default ACL of its new parent.
+    /// Symbolic links are copied as links, not followed.
+    ///
+    /// Nothing is copied unless everything fits on the volume.
+    ///
+    /// # Errors
+    /// Returns `FileError::IntoItself` if a directory would be copied into its own subtree
+    /// and `FSError::NoFreeBlocks` if the volume runs out of blocks.
+    #[trace_log]
+    fn copy_entry(&mut self, source: &str, dest: &str) -> Result<()> {
+        let abs_src = self.resolve_path(source, false)?;
+        let entry = self.lookup_entry(&abs_src, false)?;
+        let (dest_parent, dest_name) = self.destination(&abs_src, dest)?;
+        check_name(&dest_name)?;
 
-        // check if we have write permission for destnation and read permission for source
-        if !check_access_level(src_parent_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(dest_name).into());
+        if entry.file_type == FileType::Directory && is_within(&dest_parent, &abs_src) {
+            return Err(FileError::IntoItself(abs_src).into());
         }
-        if !check_access_level(dest_parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(dest_name).into());
-        }
-
-        let mut dest_is_dir = false;
 
-        if let Some(dest_entry) = dest_parent_block.get_entry(&dest_name.clone().into()) {
-            if dest_entry.file_type == FileType::Directory {
-                dest_parent_block = self.traverse_dir(abs_dest.clone())?;
-                dest_is_dir = true;
-            }
+        let mut dest_parent_block = self.traverse_dir(dest_parent.clone())?;
+        self.check_access(&dest_parent_block.parent_entry, WRITE)?;
+        if dest_parent_block
+            .get_entry(&dest_name.clone().into())
+            .is_some()
+        {
+            return Err(FileError::FileExists(dest_name.into()).into());
         }
 
-        // NOTE: this will need to be updated to handle directories
-
-        if let Some(entry) = src_parent_block.get_entry(&src_name.clone().into()) {
-            let mut new_entry = entry.clone();
-
-            if !check_access_level(new_entry.access_level, READ_WRITE) {
-                return Err(FileError::NoPermissionToWrite(new_entry.name.to_string()).into());
-            }
+        let size = self.all_or_nothing(|fs| {
+            let mut copy = fs.copy_subtree(&entry, &dest_parent_block.parent_entry)?;
+            copy.name = dest_name.into();
+            let size = copy.size as i64;
+            fs.add_dir_entry(&mut dest_parent_block, copy)?;
+            fs.write_dir_block(&dest_parent_block)?;
+            Ok(size)
+        })?;
+
+        self.update_dir_sizes(dest_parent.clone(), size)?;
+        self.touch_modified(&dest_parent)?;
+        self.touch_accessed(&abs_src)?;
+        Ok(())
+    }
+}
 
-            if !dest_is_dir {
-                new_entry.name = dest_name.clone().into();
-            }
+/// Returns `true` if `path` is `dir` or lies below it, both resolved.
+fn is_within(path: &str, dir: &str) -> bool {
+    dir == "/" || path == dir || path.starts_with(&format!("{}/", dir))
+}
 
-            if dest_parent_block
-                .get_entry(&dest_name.clone().into())
-                .is_some()
-            {
-                return Err(FileError::FileExists(dest_name.into()).into());
+impl FileSystem {
+    /// Works out where the entry at the resolved `abs_src` goes when it is copied or moved to `dest`:
+    /// into `dest` under its own name if that is a directory, otherwise to `dest` itself.
+    ///
+    /// Returns the resolved parent directory and the name.
+    ///
+    /// # Errors
+    /// Returns the error of looking `dest` up unless it just doesn't exist.
+    #[trace_log]
+    fn destination(&self, abs_src: &str, dest: &str) -> Result<(String, String)> {
+        let abs_dest = self.resolve_path(dest, false)?;
+        match self.lookup_entry(&abs_dest, true) {
+            Ok(entry) if entry.file_type == FileType::Directory => {
+                let (_, name) = split_path(abs_src.to_string());
+                Ok((self.resolve_path(&abs_dest, true)?, name))
             }
-
-            if dest_parent_block == src_parent_block {
-                dest_parent_block.remove_entry(&src_name.clone().into())?;
+            Ok(_) => Ok(split_path(abs_dest)),
+            Err(e) if matches!(e.downcast_ref(), Some(FileError::FileNotFound)) => {
+                Ok(split_path(abs_dest))
             }
-
-            dest_parent_block.add_entry(new_entry)?;
-            src_parent_block.remove_entry(&src_name.into())?;
-            self.write_dir_block(&src_parent_block)?;
-            self.write_dir_block(&dest_parent_block)?;
-            //self.update_dir(&mut src_parent_block, abs_src)?;
-            //self.update_dir(&mut dest_parent_block, abs_dest)?;
-            self.update_curr_dir()?;
-        } else {
-            return Err(FileError::FileNotFound.into());
+            Err(e) => Err(e),
         }
-
-        Ok(())
     }
 
-    /// The copy function is used to copy a file from one directory to another
+    /// Moves the entry at `source` to `dest`, replacing the entry there if `overwrite` is set.
+    ///
+    /// Both parent directories are changed in memory and only written once the entry fits,
+    /// the replaced entry is released afterwards.
+    /// If writing them fails, both are written back as they were.
     #[trace_log]
-    fn copy_entry(&mut self, source: &str, dest: &str) -> Result<()> {
-        let abs_src = absolutize_from(source, &self.curr_block.path);
-        let abs_dest = absolutize_from(dest, &self.curr_block.path);
-
-        let (src_parent, src_name) = split_path(abs_src);
-        let (dest_parent, dest_name) = split_path(abs_dest.clone());
-
-        let src_parent_block = self.traverse_dir(src_parent)?;
-        let mut dest_parent_block = self.traverse_dir(dest_parent)?;
-
-        // check if we have write permission for destnation and read permission for source
-        if !check_access_level(src_parent_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(dest_name).into());
+    fn move_to(&mut self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let abs_src = self.resolve_path(source, false)?;
+        let entry = self.lookup_entry(&abs_src, false)?;
+        let (src_parent, _) = split_path(abs_src.clone());
+        let (dest_parent, dest_name) = self.destination(&abs_src, dest)?;
+        check_name(&dest_name)?;
+        let abs_dest = format!("{}/{}", dest_parent.trim_end_matches('/'), dest_name);
+
+        if entry.file_type == FileType::Directory && is_within(&dest_parent, &abs_src) {
+            return Err(FileError::IntoItself(abs_src).into());
         }
-        if !check_access_level(dest_parent_block.parent_entry.access_level, WRITE) {
-            return Err(FileError::NoPermissionToWrite(dest_name).into());
+
+        let mut src_parent_block = self.traverse_dir(src_parent.clone())?;
+        self.check_access(&src_parent_block.parent_entry, WRITE)?;
+        let mut dest_parent_block = self.traverse_dir(dest_parent.clone())?;
+        self.check_access(&dest_parent_block.parent_entry, WRITE)?;
+        let same_parent = src_parent_block.blk_num == dest_parent_block.blk_num;
+        let snapshot = (src_parent_block.clone(), dest_parent_block.clone());
+
+        let replaced = dest_parent_block
+            .get_entry(&dest_name.clone().into())
+            .cloned();
+        if let Some(replaced) = &replaced {
+            if !overwrite {
+                return Err(FileError::FileExists(dest_name.into()).into());
+            }
+            if replaced.blk_num == entry.blk_num {
+                return Ok(());
+            }
+            self.check_replace(&entry, replaced, &abs_dest)?;
         }
 
-        let mut dest_is_dir = false;
+        let mut moved = entry.clone();
+        moved.name = dest_name.into();
+        let result = self.all_or_nothing(|fs| {
+            if let Some(replaced) = &replaced {
+                dest_parent_block.remove_entry(&replaced.name)?;
+            }
+            if same_parent {
+                dest_parent_block.remove_entry(&entry.name)?;
+            } else {
+                src_parent_block.remove_entry(&entry.name)?;
+            }
+            fs.add_dir_entry(&mut dest_parent_block, moved)?;
+            fs.write_dir_block(&dest_parent_block)?;
+            if !same_parent {
+                fs.write_dir_block(&src_parent_block)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            let (src_parent_block, dest_parent_block) = snapshot;
+            self.write_dir_block(&dest_parent_block)?;
+            if !same_parent {
+                self.write_dir_block(&src_parent_block)?;
+            }
+            return Err(e);
+        }
 
-        if let Some(dest_entry) = dest_parent_block.get_entry(&dest_name.clone().into()) {
-            if dest_entry.file_type == FileType::Directory {
-                dest_parent_block = self.traverse_dir(abs_dest.clone())?;
-                dest_is_dir = true;
+        let replaced_size = replaced.as_ref().map_or(0, |replaced| replaced.size as i64);
+        if let Some(replaced) = replaced {
+            match replaced.file_type {
+                FileType::Directory => self.remove_dir_data(&replaced, &abs_dest)?,
+                _ => self.release_data(&replaced)?,
             }
         }
 
-        // NOTE: this will need to be updated to handle directories
+        // the current directory may have moved along with the entry
+        let cwd = anchor(&self.curr_block.path);
+        if is_within(&cwd, &abs_src) {
+            self.curr_block.path = format!("{}{}", abs_dest, &cwd[abs_src.len()..]);
+        }
 
-        if let Some(entry) = self.curr_block.get_entry(&src_name.into()) {
-            let mut new_entry = entry.clone();
+        let size = entry.size as i64;
+        if same_parent {
+            self.update_dir_sizes(dest_parent.clone(), -replaced_size)?;
+        } else {
+            self.update_dir_sizes(src_parent.clone(), -size)?;
+            self.update_dir_sizes(dest_parent.clone(), size - replaced_size)?;
+            self.touch_modified(&dest_parent)?;
+        }
+        // a rename keeps the times of the entry itself, only the directories change
+        self.touch_modified(&src_parent)?;
+        self.update_curr_dir()
+    }
 
-            if !check_access_level(new_entry.access_level, READ_WRITE) {
-                return Err(FileError::NoPermissionToWrite(new_entry.name.to_string()).into());
+    /// Checks that `entry` may replace the entry `replaced` at `path`, like `rename` allows.
+    #[trace_log]
+    fn check_replace(&self, entry: &DirEntry, replaced: &DirEntry, path: &str) -> Result<()> {
+        match (entry.file_type, replaced.file_type) {
+            (FileType::Directory, FileType::Directory) => {
+                if self.read_dir_block(replaced)?.iter().next().is_some() {
+                    return Err(FileError::DirectoryNotEmpty(path.to_string()).into());
+                }
+                if anchor(&self.curr_block.path) == path {
+                    return Err(FileError::NotPermitted(path.to_string()).into());
+                }
+                Ok(())
             }
+            (FileType::Directory, _) => Err(FileError::NotADirectory(replaced.name.clone()).into()),
+            (_, FileType::Directory) => Err(FileError::FileIsDirectory.into()),
+            _ => Ok(()),
+        }
+    }
 
-            if !dest_is_dir {
-                new_entry.name = dest_name.clone().into();
+    /// Copies `entry` with everything below it to new blocks and returns the entry of the copy,
+    /// which is yet to be added to `parent`.
+    ///
+    /// Blocks taken before a failure stay taken, the caller rolls them back with `all_or_nothing`.
+    #[trace_log]
+    fn copy_subtree(&mut self, entry: &DirEntry, parent: &DirEntry) -> Result<DirEntry> {
+        let now = now();
+        let mut copy = entry.clone();
+        copy.link_count = 1;
+        copy.uid = self.credentials.uid;
+        copy.gid = self.credentials.gid;
+        copy.created = now;
+        copy.modified = now;
+        copy.accessed = now;
+        copy.acl_blk = self.inherit_acl(parent, entry.file_type)?;
+        copy.xattr_blk = self.copy_xattrs(entry)?;
+        copy.blk_num = self.get_free_block()?;
+        self.update_fat(copy.blk_num, None)?;
+
+        match entry.file_type {
+            FileType::File => {
+                self.check_access(entry, READ)?;
+                self.copy_content(entry, copy.blk_num)?;
             }
-
-            if dest_parent_block
-                .get_entry(&dest_name.clone().into())
-                .is_some()
-            {
-                return Err(FileError::FileExists(dest_name.into()).into());
+            FileType::Symlink => {
+                let data = self.read_file_data(entry.blk_num)?;
+                self.write_data(&data, copy.blk_num)?;
             }
-
-            match new_entry.file_type {
-                FileType::File => {
-                    let data = self.read_file_data(new_entry.blk_num)?;
-                    new_entry.blk_num = self.get_free_block()?;
-                    self.write_data(&data, new_entry.blk_num)?;
-                }
-                FileType::Directory => {
-                    new_entry.size = 0;
-                    let block = DirBlock::default();
-                    new_entry.blk_num = self.get_free_block()?;
-                    self.write_data(&block, new_entry.blk_num)?;
+            FileType::Directory => {
+                self.check_access(entry, READ | EXECUTE)?;
+                let source = self.read_dir_block(entry)?;
+                let mut block = DirBlock::new(copy.clone(), copy.blk_num);
+                block.index = self.fat.options.dir_index;
+
+                copy.size = 0;
+                for child in source.iter_sorted() {
+                    let child_copy = self.copy_subtree(child, &copy)?;
+                    copy.size += child_copy.size;
+                    self.add_dir_entry(&mut block, child_copy)?;
                 }
+                self.write_dir_block(&block)?;
             }
+        }
 
-            dest_parent_block.add_entry(new_entry)?;
-            self.write_dir_block(&dest_parent_block)?;
-            self.update_curr_dir()?;
+        Ok(copy)
+    }
+
+    /// Collects `entry` at `path` and everything below it, parents before their children.
+    ///
+    /// Symbolic links are collected but not followed.
+    #[trace_log]
+    fn collect_subtree(
+        &self,
+        path: String,
+        entry: DirEntry,
+        entries: &mut Vec<(String, DirEntry)>,
+    ) -> Result<()> {
+        if entry.file_type == FileType::Directory {
+            self.check_access(&entry, READ | EXECUTE)?;
+            let block = self.read_dir_block(&entry)?;
+            entries.push((path.clone(), entry));
+            for child in block.iter_sorted() {
+                let child_path = format!("{}/{}", path.trim_end_matches('/'), child.name);
+                self.collect_subtree(child_path, child.clone(), entries)?;
+            }
         } else {
-            return Err(FileError::FileNotFound.into());
+            entries.push((path, entry));
         }
-
         Ok(())
     }
 }
 
-#[trace_log]
-fn convert_str_to_u8_digit(s: &str) -> Result<u8, std::num::ParseIntError> {
-    s.parse::<u8>()
-}
-
-impl Permissions for FileSystem {
+impl PermissionHandling for FileSystem {
+    /// Changes the mode of the entry at `path`, given as octal digits like `644`
+    /// or symbolic clauses like `u+x,g-w`.
+    ///
+    /// Only the owner and the superuser may change the mode.
     #[trace_log]
     fn change_permissions(&mut self, path: &str, permissions: &str) -> Result<()> {
         let abs_path = absolutize_from(path, &self.curr_block.path);
-        let (parent, name) = split_path(abs_path);
-        let mut parent_block = self.traverse_dir(parent)?;
+        let change = permissions.parse::<ModeChange>()?;
 
-        // convert safley the &str permissions into a u8
-        let permissions = convert_str_to_u8_digit(permissions)?;
-
-        if let Some(entry) = parent_block.clone().get_entry_mut(&name.into()) {
-            if permissions > READ_WRITE_EXECUTE {
-                return Err(FileError::InvalidAccessLevel(permissions).into());
-            }
+        let entry = self.lookup_entry(&anchor(&abs_path), false)?;
+        self.check_owner(&entry)?;
+        let mode = change.apply(entry.permissions()).bits();
+        self.update_entry_at(&abs_path, |entry| entry.mode = mode)
+    }
 
-            entry.access_level = permissions;
-            parent_block.update_entry(entry)?;
-            self.write_dir_block(&parent_block)?;
+    /// Changes the mode of the entry at `path` and of everything below it like `change_permissions`.
+    ///
+    /// Nothing is changed unless the caller owns every entry of the subtree.
+    #[trace_log]
+    fn change_permissions_recursive(&mut self, path: &str, permissions: &str) -> Result<()> {
+        let abs_path = self.resolve_path(&absolutize_from(path, &self.curr_block.path), false)?;
+        let change = permissions.parse::<ModeChange>()?;
 
-            if entry.file_type == FileType::Directory {
-                let mut block = self.read_dir_block(entry)?;
-                block
-                    .entries
-                    .iter_mut()
-                    .for_each(|entry| entry.access_level = permissions);
-                self.write_dir_block(&block)?;
-            }
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if abs_path == "/" {
+            return Err(FileError::NotPermitted(abs_path).into());
+        }
+        let mut entries = Vec::new();
+        self.collect_subtree(abs_path, entry, &mut entries)?;
+        for (_, entry) in &entries {
+            self.check_owner(entry)?;
+        }
 
-            self.update_curr_dir()?;
-        } else {
-            return Err(FileError::FileNotFound.into());
+        // children first, so every directory on the way stays searchable until its own turn
+        for (path, entry) in entries.into_iter().rev() {
+            let mode = change.apply(entry.permissions()).bits();
+            self.update_entry_at(&path, |entry| entry.mode = mode)?;
         }
         Ok(())
     }
+
+    /// Changes the owner and/or group of the entry at `path`.
+    ///
+    /// Only the superuser may give an entry away,
+    /// the owner may still change its group.
+    #[trace_log]
+    fn change_owner(&mut self, path: &str, uid: Option<u16>, gid: Option<u16>) -> Result<()> {
+        let abs_path = absolutize_from(path, &self.curr_block.path);
+        let entry = self.lookup_entry(&anchor(&abs_path), false)?;
+
+        if uid.is_some_and(|uid| uid != entry.uid) && !self.credentials.is_root() {
+            return Err(FileError::NotPermitted(entry.name.to_string()).into());
+        }
+        self.check_owner(&entry)?;
+
+        self.update_entry_at(&abs_path, |entry| {
+            entry.uid = uid.unwrap_or(entry.uid);
+            entry.gid = gid.unwrap_or(entry.gid);
+        })
+    }
 }
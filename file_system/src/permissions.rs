@@ -0,0 +1,204 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bitflags::bitflags;
+
+use crate::dir_entry::DirEntry;
+use crate::errors::FileError;
+
+bitflags! {
+    /// The 9 access bits of an entry, read, write and execute for its owner, its group and others.
+    ///
+    /// Displays as `rwxr-xr-x` and parses from either that form or three octal digits like `755`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Permissions: u16 {
+        const OWNER_READ = 0o400;
+        const OWNER_WRITE = 0o200;
+        const OWNER_EXECUTE = 0o100;
+        const GROUP_READ = 0o040;
+        const GROUP_WRITE = 0o020;
+        const GROUP_EXECUTE = 0o010;
+        const OTHER_READ = 0o004;
+        const OTHER_WRITE = 0o002;
+        const OTHER_EXECUTE = 0o001;
+
+        const OWNER = 0o700;
+        const GROUP = 0o070;
+        const OTHER = 0o007;
+        const READ = 0o444;
+        const WRITE = 0o222;
+        const EXECUTE = 0o111;
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions::empty()
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for class in [Permissions::OWNER, Permissions::GROUP, Permissions::OTHER] {
+            for (bits, symbol) in [
+                (Permissions::READ, 'r'),
+                (Permissions::WRITE, 'w'),
+                (Permissions::EXECUTE, 'x'),
+            ] {
+                let symbol = if self.contains(class & bits) {
+                    symbol
+                } else {
+                    '-'
+                };
+                write!(f, "{}", symbol)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = FileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FileError::InvalidAccessLevel(s.to_string());
+
+        if s.len() == 3 && s.chars().all(|c| ('0'..='7').contains(&c)) {
+            let bits = u16::from_str_radix(s, 8).map_err(|_| invalid())?;
+            return Ok(Permissions::from_bits_truncate(bits));
+        }
+
+        if s.len() != 9 {
+            return Err(invalid());
+        }
+        let mut permissions = Permissions::empty();
+        for (i, c) in s.chars().enumerate() {
+            let bit = 1 << (8 - i);
+            match (c, i % 3) {
+                ('-', _) => {}
+                ('r', 0) | ('w', 1) | ('x', 2) => {
+                    permissions |= Permissions::from_bits_truncate(bit)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(permissions)
+    }
+}
+
+/// How a symbolic clause combines its bits with the current mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Remove,
+    Set,
+}
+
+/// One `who op perms` part of a symbolic mode, like `g-w`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Clause {
+    who: Permissions,
+    op: Op,
+    perms: Permissions,
+}
+
+/// A change `chmod` makes to the mode of an entry.
+///
+/// Parses from an absolute mode like `755` or `rwxr-xr-x`
+/// or from comma separated symbolic clauses like `u+x,g-w,o=r`.
+/// A clause without `u`, `g`, `o` or `a` applies to every class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeChange {
+    absolute: Option<Permissions>,
+    clauses: Vec<Clause>,
+}
+
+impl ModeChange {
+    /// Returns the mode `current` becomes after this change.
+    pub fn apply(&self, current: Permissions) -> Permissions {
+        if let Some(absolute) = self.absolute {
+            return absolute;
+        }
+
+        self.clauses.iter().fold(current, |mode, clause| {
+            let bits = clause.who & clause.perms;
+            match clause.op {
+                Op::Add => mode | bits,
+                Op::Remove => mode - bits,
+                Op::Set => (mode - clause.who) | bits,
+            }
+        })
+    }
+}
+
+impl FromStr for ModeChange {
+    type Err = FileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(absolute) = s.parse::<Permissions>() {
+            return Ok(ModeChange {
+                absolute: Some(absolute),
+                clauses: Vec::new(),
+            });
+        }
+
+        let invalid = || FileError::InvalidAccessLevel(s.to_string());
+        let mut clauses = Vec::new();
+        for part in s.split(',') {
+            let mut chars = part.chars().peekable();
+
+            let mut who = Permissions::empty();
+            while let Some(class) = chars.peek().and_then(|c| match c {
+                'u' => Some(Permissions::OWNER),
+                'g' => Some(Permissions::GROUP),
+                'o' => Some(Permissions::OTHER),
+                'a' => Some(Permissions::all()),
+                _ => None,
+            }) {
+                who |= class;
+                chars.next();
+            }
+            if who.is_empty() {
+                who = Permissions::all();
+            }
+
+            // a clause may hold several operations, like `u+r-w`
+            let mut ops = 0;
+            while let Some(c) = chars.next() {
+                let op = match c {
+                    '+' => Op::Add,
+                    '-' => Op::Remove,
+                    '=' => Op::Set,
+                    _ => return Err(invalid()),
+                };
+                let mut perms = Permissions::empty();
+                while let Some(bits) = chars.peek().and_then(|c| match c {
+                    'r' => Some(Permissions::READ),
+                    'w' => Some(Permissions::WRITE),
+                    'x' => Some(Permissions::EXECUTE),
+                    _ => None,
+                }) {
+                    perms |= bits;
+                    chars.next();
+                }
+                clauses.push(Clause { who, op, perms });
+                ops += 1;
+            }
+            if ops == 0 {
+                return Err(invalid());
+            }
+        }
+
+        Ok(ModeChange {
+            absolute: None,
+            clauses,
+        })
+    }
+}
+
+impl DirEntry {
+    /// Returns the mode of this entry as typed permissions.
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_bits_truncate(self.mode)
+    }
+}
@@ -1,3 +1,16 @@
+pub use crate::acl::{Acl, AclEntry, AclKind, AclTag};
+pub use crate::credentials::{Credentials, NOBODY_GID, NOBODY_UID, ROOT_GID, ROOT_UID};
+pub use crate::dir_entry::FileType;
 pub use crate::errors::*;
+pub use crate::files::StdinInput;
+pub use crate::format::{DirIndex, FormatOptions};
+pub use crate::handle::{FileHandle, OpenOptions};
+pub use crate::info::EntryInfo;
+pub use crate::permissions::{ModeChange, Permissions};
+pub use crate::times::AtimePolicy;
 pub use crate::traits::*;
-pub use crate::{FileSystem, StdIOHandler};
+pub use crate::{FileSystem, StdIOHandler, MAX_FILE_SIZE};
+pub use crate::usage::{DirUsage, VolumeUsage};
+pub use crate::users::{User, FIRST_USER_ID, PASSWD_PATH, SHADOW_PATH};
+pub use crate::walk::{ReadDir, Walk, WalkEntry};
+pub use crate::xattr::{XATTR_NAME_MAX, XATTR_VALUE_MAX};
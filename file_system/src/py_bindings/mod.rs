@@ -120,6 +120,7 @@ fn setup_pyo3_logger() {
 #[pyo3(name = "RusticFS")]
 fn rustic_fs(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<crate::FileSystem>()?;
+    m.add_class::<crate::info::EntryInfo>()?;
     m.add_function(wrap_pyfunction!(setup_logger, m)?)?;
     m.add_function(wrap_pyfunction!(setup_file_logger, m)?)?;
     m.add_function(wrap_pyfunction!(setup_pyo3_logger, m)?)?;
@@ -86,6 +86,16 @@ impl FileSystem {
         py_wrap!(self.update_dir(entry, path))
     }
 
+    #[pyo3(name = "update_dir_sizes")]
+    pub fn py_update_dir_sizes(&mut self, path: String, delta: i64) -> PyResult<()> {
+        py_wrap!(self.update_dir_sizes(path, delta))
+    }
+
+    #[pyo3(name = "recompute_dir_sizes")]
+    pub fn py_recompute_dir_sizes(&mut self) -> PyResult<u64> {
+        py_wrap!(self.recompute_dir_sizes(), u64)
+    }
+
     #[pyo3(name = "traverse_dir")]
     pub fn py_traverse_dir(&self, path: String) -> PyResult<DirBlock> {
         py_wrap!(self.traverse_dir(path), DirBlock)
@@ -102,14 +112,23 @@ impl FileSystem {
         py_wrap!(self.change_dir(path))
     }
 
+    #[pyo3(name = "cwd")]
+    pub fn py_cwd(&self) -> String {
+        self.cwd()
+    }
+
     #[pyo3(name = "print_working_dir")]
     pub fn py_print_working_dir(&mut self) -> PyResult<()> {
         py_wrap!(self.print_working_dir())
     }
 
-    #[pyo3(name = "format")]
-    pub fn py_format(&mut self) -> PyResult<()> {
-        py_wrap!(self.format())
+    #[pyo3(name = "format", signature = (dir_index=None))]
+    pub fn py_format(&mut self, dir_index: Option<&str>) -> PyResult<()> {
+        let dir_index = match dir_index {
+            Some(dir_index) => dir_index.parse::<DirIndex>()?,
+            None => DirIndex::default(),
+        };
+        py_wrap!(self.format_with(FormatOptions { dir_index }))
     }
 
     #[pyo3(name = "create_file")]
@@ -123,6 +142,16 @@ impl FileSystem {
         py_wrap!(self.create_file_with_content(path, content))
     }
 
+    #[pyo3(name = "create_file_with_bytes")]
+    pub fn py_create_file_with_bytes(&mut self, path: &str, content: &[u8]) -> PyResult<()> {
+        py_wrap!(self.create_file_with_bytes(path, content))
+    }
+
+    #[pyo3(name = "create_file_from_base64")]
+    pub fn py_create_file_from_base64(&mut self, path: &str, encoded: &str) -> PyResult<()> {
+        py_wrap!(self.create_file_from_base64(path, encoded))
+    }
+
     #[pyo3(name = "create_dir")]
     pub fn py_create_dir(&mut self, path: &str) -> PyResult<()> {
         py_wrap!(self.create_dir(path))
@@ -139,19 +168,164 @@ impl FileSystem {
         py_wrap!(self.read_file(path))
     }
 
+    #[pyo3(name = "read")]
+    pub fn py_read(&mut self, path: &str) -> PyResult<Vec<u8>> {
+        py_wrap!(self.read(path), Vec<u8>)
+    }
+
+    #[pyo3(name = "hexdump")]
+    pub fn py_hexdump(&mut self, path: &str) -> PyResult<()> {
+        py_wrap!(self.hexdump(path))
+    }
+
+    #[pyo3(name = "xxd")]
+    pub fn py_xxd(&mut self, path: &str) -> PyResult<()> {
+        py_wrap!(self.xxd(path))
+    }
+
     #[pyo3(name = "append_file")]
     pub fn py_append_file(&mut self, source: &str, dest: &str) -> PyResult<()> {
         py_wrap!(self.append_file(source, dest))
     }
 
+    #[pyo3(name = "append_bytes")]
+    pub fn py_append_bytes(&mut self, path: &str, data: &[u8]) -> PyResult<()> {
+        py_wrap!(self.append_bytes(path, data))
+    }
+
+    #[pyo3(name = "write_at")]
+    pub fn py_write_at(&mut self, path: &str, offset: u64, data: &[u8]) -> PyResult<()> {
+        py_wrap!(self.write_at(path, offset, data))
+    }
+
+    #[pyo3(name = "read_range")]
+    pub fn py_read_range(&mut self, path: &str, offset: u64, len: u64) -> PyResult<Vec<u8>> {
+        py_wrap!(self.read_range(path, offset, len), Vec<u8>)
+    }
+
+    #[pyo3(name = "truncate")]
+    pub fn py_truncate(&mut self, path: &str, len: u64) -> PyResult<()> {
+        py_wrap!(self.truncate(path, len))
+    }
+
     #[pyo3(name = "list_dir")]
     pub fn py_list_dir(&mut self) -> PyResult<()> {
         py_wrap!(self.list_dir())
     }
 
-    #[pyo3(name = "change_permissions")]
-    pub fn py_change_permissions(&mut self, path: &str, access_level: &str) -> PyResult<()> {
-        py_wrap!(self.change_permissions(path, access_level))
+    #[pyo3(name = "read_dir", signature = (path="."))]
+    pub fn py_read_dir(&self, path: &str) -> PyResult<Vec<EntryInfo>> {
+        py_wrap!(self.read_dir(path).map(Iterator::collect), Vec<EntryInfo>)
+    }
+
+    #[pyo3(name = "walk", signature = (path=".", min_depth=0, max_depth=None, contents_first=false))]
+    pub fn py_walk(
+        &self,
+        path: &str,
+        min_depth: usize,
+        max_depth: Option<usize>,
+        contents_first: bool,
+    ) -> PyResult<Vec<String>> {
+        let walk = self
+            .walk(path)
+            .min_depth(min_depth)
+            .max_depth(max_depth.unwrap_or(usize::MAX))
+            .contents_first(contents_first);
+        py_wrap!(walk.map(|entry| entry.map(|e| e.path)).collect::<anyhow::Result<_>>(), Vec<String>)
+    }
+
+    #[pyo3(name = "change_permissions", signature = (path, access_level, recursive=false))]
+    pub fn py_change_permissions(
+        &mut self,
+        path: &str,
+        access_level: &str,
+        recursive: bool,
+    ) -> PyResult<()> {
+        if recursive {
+            py_wrap!(self.change_permissions_recursive(path, access_level))
+        } else {
+            py_wrap!(self.change_permissions(path, access_level))
+        }
+    }
+
+    #[pyo3(name = "change_owner", signature = (path, uid=None, gid=None))]
+    pub fn py_change_owner(
+        &mut self,
+        path: &str,
+        uid: Option<u16>,
+        gid: Option<u16>,
+    ) -> PyResult<()> {
+        py_wrap!(self.change_owner(path, uid, gid))
+    }
+
+    #[pyo3(name = "get_acl")]
+    pub fn py_get_acl(&self, path: &str) -> PyResult<Vec<String>> {
+        py_wrap!(
+            self.get_acl(path)
+                .map(|acl| acl.entries().iter().map(ToString::to_string).collect()),
+            Vec<String>
+        )
+    }
+
+    #[pyo3(name = "modify_acl")]
+    pub fn py_modify_acl(&mut self, path: &str, specs: &str) -> PyResult<()> {
+        py_wrap!(self.modify_acl(path, specs))
+    }
+
+    #[pyo3(name = "remove_acl_entries")]
+    pub fn py_remove_acl_entries(&mut self, path: &str, specs: &str) -> PyResult<()> {
+        py_wrap!(self.remove_acl_entries(path, specs))
+    }
+
+    #[pyo3(name = "clear_acl")]
+    pub fn py_clear_acl(&mut self, path: &str) -> PyResult<()> {
+        py_wrap!(self.set_acl(path, &Acl::default()))
+    }
+
+    #[pyo3(name = "get_xattr")]
+    pub fn py_get_xattr(&self, path: &str, name: &str) -> PyResult<Vec<u8>> {
+        py_wrap!(self.get_xattr(path, name), Vec<u8>)
+    }
+
+    #[pyo3(name = "set_xattr")]
+    pub fn py_set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> PyResult<()> {
+        py_wrap!(self.set_xattr(path, name, value))
+    }
+
+    #[pyo3(name = "list_xattr")]
+    pub fn py_list_xattr(&self, path: &str) -> PyResult<Vec<String>> {
+        py_wrap!(self.list_xattr(path), Vec<String>)
+    }
+
+    #[pyo3(name = "remove_xattr")]
+    pub fn py_remove_xattr(&mut self, path: &str, name: &str) -> PyResult<()> {
+        py_wrap!(self.remove_xattr(path, name))
+    }
+
+    #[pyo3(name = "set_credentials")]
+    pub fn py_set_credentials(&mut self, uid: u16, gid: u16) -> PyResult<()> {
+        self.set_credentials(Credentials::new(uid, gid));
+        Ok(())
+    }
+
+    #[pyo3(name = "add_user")]
+    pub fn py_add_user(&mut self, name: &str) -> PyResult<u16> {
+        py_wrap!(self.add_user(name).map(|user| user.uid), u16)
+    }
+
+    #[pyo3(name = "set_password")]
+    pub fn py_set_password(&mut self, name: &str, password: &str) -> PyResult<()> {
+        py_wrap!(self.set_password(name, password))
+    }
+
+    #[pyo3(name = "login")]
+    pub fn py_login(&mut self, name: &str, password: &str) -> PyResult<u16> {
+        py_wrap!(self.login(name, password).map(|user| user.uid), u16)
+    }
+
+    #[pyo3(name = "switch_user", signature = (name, password=None))]
+    pub fn py_switch_user(&mut self, name: &str, password: Option<&str>) -> PyResult<u16> {
+        py_wrap!(self.switch_user(name, password).map(|user| user.uid), u16)
     }
 
     #[pyo3(name = "copy_entry")]
@@ -159,11 +333,56 @@ impl FileSystem {
         py_wrap!(self.copy_entry(source, dest))
     }
 
-    #[pyo3(name = "move_entry")]
-    pub fn py_move_entry(&mut self, source: &str, dest: &str) -> PyResult<()> {
-        py_wrap!(self.move_entry(source, dest))
+    #[pyo3(name = "move_entry", signature = (source, dest, overwrite=false))]
+    pub fn py_move_entry(&mut self, source: &str, dest: &str, overwrite: bool) -> PyResult<()> {
+        if overwrite {
+            py_wrap!(self.move_entry_overwrite(source, dest))
+        } else {
+            py_wrap!(self.move_entry(source, dest))
+        }
     }
     
+    #[pyo3(name = "df")]
+    pub fn py_df(&mut self) -> PyResult<()> {
+        py_wrap!(self.df())
+    }
+
+    #[pyo3(name = "du", signature = (path, summarize=false))]
+    pub fn py_du(&mut self, path: &str, summarize: bool) -> PyResult<()> {
+        py_wrap!(self.du(path, summarize))
+    }
+
+    #[pyo3(name = "create_symlink")]
+    pub fn py_create_symlink(&mut self, target: &str, path: &str) -> PyResult<()> {
+        py_wrap!(self.create_symlink(target, path))
+    }
+
+    #[pyo3(name = "create_hard_link")]
+    pub fn py_create_hard_link(&mut self, target: &str, path: &str) -> PyResult<()> {
+        py_wrap!(self.create_hard_link(target, path))
+    }
+
+    #[pyo3(name = "read_link")]
+    pub fn py_read_link(&self, path: &str) -> PyResult<String> {
+        py_wrap!(self.read_link(path), String)
+    }
+
+    #[pyo3(name = "stat")]
+    pub fn py_stat(&mut self, path: &str) -> PyResult<()> {
+        py_wrap!(self.stat(path))
+    }
+
+    #[pyo3(name = "metadata")]
+    pub fn py_metadata(&self, path: &str) -> PyResult<EntryInfo> {
+        py_wrap!(self.metadata(path), EntryInfo)
+    }
+
+    #[pyo3(name = "set_atime_policy")]
+    pub fn py_set_atime_policy(&mut self, policy: &str) -> PyResult<()> {
+        self.set_atime_policy(policy.parse::<AtimePolicy>()?);
+        Ok(())
+    }
+
     //#[cfg(not(PyPy))]
     #[pyo3(name = "execute_py")]
     pub fn py_execute_py(&mut self, file_path: &str) -> PyResult<()> {
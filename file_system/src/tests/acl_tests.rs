@@ -0,0 +1,137 @@
+use crate::errors::FileError;
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_acl_entries() -> anyhow::Result<()> {
+    let entry = "d:deny:u:1000:w".parse::<AclEntry>()?;
+    assert_eq!(
+        entry,
+        AclEntry {
+            default: true,
+            kind: AclKind::Deny,
+            tag: AclTag::User(1000),
+            perms: 0o2,
+        }
+    );
+    assert_eq!(entry.to_string(), "default:deny:user:1000:-w-");
+    assert_eq!(
+        "group:100:r-x".parse::<AclEntry>()?.to_string(),
+        "group:100:r-x"
+    );
+    for spec in [
+        "u",
+        "u:x:rwx",
+        "o:1000:rwx",
+        "u:1000:rwz",
+        "u:1000:rwx:x",
+        "deny:d:u:1:r",
+    ] {
+        assert!(spec.parse::<AclEntry>().is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_acl_permission_check() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("shared")?;
+    fs.create_file_with_content("shared/notes", "Hello")?;
+    fs.change_permissions("shared/notes", "600")?;
+
+    // a named user gets in although the other bits say no
+    fs.modify_acl("shared/notes", "u:1000:rw,g:100:r")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    assert!(fs.read_file("shared/notes").is_ok());
+    fs.set_credentials(Credentials::new(1001, 100));
+    assert!(fs.read_file("shared/notes").is_ok());
+    fs.set_credentials(Credentials::new(1002, 1002));
+    assert!(fs.read_file("shared/notes").is_err());
+
+    // deny entries win over the bits a user would get otherwise
+    fs.set_credentials(Credentials::root());
+    fs.change_permissions("shared/notes", "644")?;
+    fs.modify_acl("shared/notes", "deny:u:1002:r")?;
+    fs.modify_acl("shared", "deny:g:100:x")?;
+    fs.set_credentials(Credentials::new(1002, 1002));
+    let err = fs.read_file("shared/notes").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToRead(_))
+    ));
+    // denying one bit leaves the others
+    fs.set_credentials(Credentials::root());
+    fs.create_file_with_content("shared/log", "Hello")?;
+    fs.change_permissions("shared/log", "666")?;
+    fs.modify_acl("shared/log", "deny:u:1002:r")?;
+    fs.create_file_with_content("line", "World")?;
+    fs.set_credentials(Credentials::new(1002, 1002));
+    assert!(fs.read_file("shared/log").is_err());
+    fs.append_file("line", "shared/log")?;
+    fs.set_credentials(Credentials::new(1001, 100));
+    assert!(fs.read_file("shared/notes").is_err());
+
+    // only the owner may change the list and defaults only go on directories
+    assert!(fs.modify_acl("shared/notes", "u:1001:rwx").is_err());
+    fs.set_credentials(Credentials::root());
+    assert!(fs.modify_acl("shared/notes", "d:u:1001:r").is_err());
+    fs.remove_acl_entries("shared", "deny:g:100")?;
+    fs.set_credentials(Credentials::new(1001, 100));
+    assert!(fs.read_file("shared/notes").is_ok());
+
+    fs.set_credentials(Credentials::root());
+    let acl = fs.get_acl("shared/notes")?;
+    let specs = acl
+        .entries()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        specs,
+        ["user:1000:rw-", "group:100:r--", "deny:user:1002:r--"]
+    );
+    fs.set_acl("shared/notes", &Acl::default())?;
+    assert_eq!(fs.lookup_entry("/shared/notes", false)?.acl_blk, 0);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_default_acl_inheritance() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("project")?;
+    fs.change_permissions("project", "700")?;
+    fs.modify_acl("project", "u:1000:rwx,d:u:1000:rwx,d:deny:g:100:w")?;
+
+    fs.create_file_with_content("project/readme", "Hello")?;
+    fs.create_dir("project/src")?;
+    fs.create_file_with_content("loose", "Hello")?;
+    fs.copy_entry("loose", "project/copy")?;
+
+    let file_acl = fs.get_acl("project/readme")?;
+    assert!(file_acl.entries().iter().all(|entry| !entry.default));
+    assert_eq!(file_acl.entries().len(), 2);
+    assert_eq!(fs.get_acl("project/copy")?, file_acl);
+    // a new directory hands the defaults down further
+    assert_eq!(fs.get_acl("project/src")?.entries().len(), 4);
+    assert!(fs.get_acl("loose")?.is_empty());
+
+    fs.set_credentials(Credentials::new(1000, 1000));
+    fs.create_file_with_content("project/src/main", "Hello")?;
+    assert_eq!(fs.get_acl("project/src/main")?, file_acl);
+
+    // the blocks of the lists are freed with their entries
+    fs.set_credentials(Credentials::root());
+    let free = fs.volume_usage()?.free_bytes();
+    fs.delete_file("project/copy")?;
+    fs.delete_dir("project/src")?;
+    assert!(fs.volume_usage()?.free_bytes() > free);
+    fs.create_file_with_content("after", "Hello")?;
+    assert!(fs.get_acl("after")?.is_empty());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
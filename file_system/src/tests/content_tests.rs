@@ -0,0 +1,373 @@
+use rustic_disk::traits::BlockStorage;
+
+use crate::errors::{FSError, FileError};
+use crate::fat::MAX_HOLES;
+use crate::prelude::*;
+use crate::tests::MockInput;
+use crate::utils::hex;
+use crate::FileSystem;
+
+#[test]
+fn test_read_range() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("docs")?;
+    let content = (0..20_000)
+        .map(|i| (b'a' + (i % 26) as u8) as char)
+        .collect::<String>();
+    fs.create_file_with_content("docs/big", &content)?;
+    assert_eq!(fs.lookup_entry("/docs/big", false)?.size, 20_000);
+
+    // ranges crossing block boundaries and running past the end
+    assert_eq!(fs.read_range("docs/big", 0, 3)?, b"abc");
+    assert_eq!(
+        fs.read_range("docs/big", 4090, 12)?,
+        &content.as_bytes()[4090..4102]
+    );
+    assert_eq!(fs.read_range("docs/big", 19_998, 10)?, b"ef");
+    assert!(fs.read_range("docs/big", 20_000, 10)?.is_empty());
+    assert_eq!(fs.read_range("docs/big", 0, u64::MAX)?, content.as_bytes());
+
+    // only the blocks holding the range are read
+    let blk_num = fs.lookup_entry("/docs/big", false)?.blk_num;
+    fs.disk.write_raw_data(blk_num as usize, &[0xff; 16])?;
+    assert_eq!(
+        fs.read_range("docs/big", 8192, 3)?,
+        &content.as_bytes()[8192..8195]
+    );
+
+    let err = fs.read_range("docs", 0, 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::FileIsDirectory)
+    ));
+    fs.change_permissions("docs/big", "600")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    let err = fs.read_range("docs/big", 0, 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToRead(_))
+    ));
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_append_in_place() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("log", &"a".repeat(4090))?;
+    let blk_num = fs.lookup_entry("/log", false)?.blk_num;
+    let used = fs.volume_usage()?.used_blocks;
+
+    // the last block is filled up before a new one is linked
+    fs.append_bytes("log", b"bbbbbb")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used);
+    fs.append_bytes("log", b"cccc")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used + 1);
+    assert_eq!(fs.lookup_entry("/log", false)?.blk_num, blk_num);
+    assert_eq!(fs.lookup_entry("/log", false)?.size, 4100);
+    assert_eq!(fs.read_range("log", 4088, 100)?, b"aabbbbbbcccc");
+
+    // nothing is put between the old and the new content
+    fs.create_file_with_content("words", "Hello")?;
+    fs.create_file_with_content("more", ", World")?;
+    fs.append_file("more", "words")?;
+    fs.append_input("words", &mut MockInput::new("!"))?;
+    fs.append_bytes("words", &[0, 1, 2])?;
+    assert_eq!(fs.read_range("words", 0, 100)?, b"Hello, World!\0\x01\x02");
+
+    assert!(fs.append_bytes("/", b"x").is_err());
+    assert!(fs.append_bytes("missing", b"x").is_err());
+
+    // an append that runs out of space leaves the file as it was
+    let free_blocks = fs.volume_usage()?.free_bytes() / 4096;
+    fs.create_file_with_content("filler", &"f".repeat((free_blocks as usize - 2) * 4096))?;
+    let free = fs.volume_usage()?.free_bytes();
+    let err = fs.append_bytes("log", &[b'd'; 5 * 4096]).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FSError::NoFreeBlocks)));
+    assert_eq!(fs.volume_usage()?.free_bytes(), free);
+    assert_eq!(fs.lookup_entry("/log", false)?.size, 4100);
+    assert_eq!(fs.read_range("log", 4088, 100)?, b"aabbbbbbcccc");
+    fs.append_bytes("log", b"dd")?;
+    assert_eq!(fs.read_range("log", 4096, 100)?, b"ccccdd");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_binary_content() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let bytes = (0..=255).cycle().take(5000).collect::<Vec<u8>>();
+    fs.create_file_with_bytes("blob", &bytes)?;
+    assert_eq!(fs.read_range("blob", 0, u64::MAX)?, bytes);
+    // invalid UTF-8 is still shown instead of failing
+    fs.read_file("blob")?;
+    fs.hexdump("blob")?;
+
+    // "Hello" and the bytes 0, 255
+    fs.create_file_from_base64("decoded", "SGVs\nbG8A/w==\n")?;
+    assert_eq!(fs.read_range("decoded", 0, 100)?, b"Hello\0\xff");
+    let err = fs
+        .create_file_from_base64("bad", "not base64!")
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::InvalidBase64(_))
+    ));
+    assert!(fs.lookup_entry("/bad", false).is_err());
+
+    assert_eq!(
+        hex::hexdump(b"Hello, World!\n\x00\xff"),
+        "00000000  48 65 6c 6c 6f 2c 20 57  6f 72 6c 64 21 0a 00 ff  |Hello, World!...|\n\
+         00000010"
+    );
+    assert_eq!(
+        hex::xxd(b"Hello, World!\n"),
+        "00000000: 4865 6c6c 6f2c 2057 6f72 6c64 210a       Hello, World!."
+    );
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+/// A reader that yields `ok` bytes and then fails.
+struct FailingReader {
+    ok: usize,
+}
+
+impl std::io::Read for FailingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.ok == 0 {
+            return Err(std::io::Error::other("disk unplugged"));
+        }
+        let n = buf.len().min(self.ok);
+        buf[..n].fill(b'z');
+        self.ok -= n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_stream_from_reader() -> anyhow::Result<()> {
+    use std::io::Read;
+
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let used = fs.volume_usage()?.used_blocks;
+
+    fs.create_file_from_reader("big", std::io::repeat(b'x').take(50_000))?;
+    assert_eq!(fs.lookup_entry("/big", false)?.size, 50_000);
+    assert_eq!(fs.volume_usage()?.used_blocks, used + 13);
+    assert_eq!(fs.read_range("big", 49_998, 10)?, b"xx");
+    assert!(fs
+        .create_file_from_reader("big", std::io::empty())
+        .is_err());
+
+    // a failing reader leaves neither a file nor taken blocks behind
+    let used = fs.volume_usage()?.used_blocks;
+    assert!(fs
+        .create_file_from_reader("broken", FailingReader { ok: 10_000 })
+        .is_err());
+    assert!(fs.lookup_entry("/broken", false).is_err());
+    assert_eq!(fs.volume_usage()?.used_blocks, used);
+
+    // replacing keeps the old content if reading fails
+    assert!(fs
+        .write_file_from_reader("big", FailingReader { ok: 5000 })
+        .is_err());
+    assert_eq!(fs.lookup_entry("/big", false)?.size, 50_000);
+    assert_eq!(fs.volume_usage()?.used_blocks, used);
+
+    // every name shows the new content and the old blocks are freed
+    fs.create_hard_link("big", "alias")?;
+    fs.write_file_from_reader("alias", &b"short"[..])?;
+    assert_eq!(fs.read_range("big", 0, 100)?, b"short");
+    assert_eq!(fs.lookup_entry("/big", false)?.size, 5);
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 12);
+
+    fs.write_file_from_reader("new", &b"fresh"[..])?;
+    assert_eq!(fs.read_range("new", 0, 100)?, b"fresh");
+
+    // zeros take no blocks, but the file still can't grow past the volume
+    let zeros = std::io::repeat(0).take(MAX_FILE_SIZE + 1);
+    let err = fs.write_file_from_reader("new", zeros).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::FileTooLarge)));
+    assert_eq!(fs.read_range("new", 0, 100)?, b"fresh");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_truncate() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("docs")?;
+    fs.create_file_with_content("docs/big", &"a".repeat(10_000))?;
+    fs.create_hard_link("docs/big", "alias")?;
+    let used = fs.volume_usage()?.used_blocks;
+
+    // shrinking frees the blocks past the new end
+    fs.truncate("docs/big", 4097)?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 1);
+    assert_eq!(fs.metadata("alias")?.size, 4097);
+    assert_eq!(fs.metadata("docs")?.size, 4097);
+    fs.truncate("alias", 3)?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 2);
+    assert_eq!(fs.read("docs/big")?, b"aaa");
+
+    // growing reads back zeros, not the old content, and leaves a hole
+    fs.truncate("docs/big", 9000)?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 2);
+    let content = fs.read("alias")?;
+    assert_eq!(content.len(), 9000);
+    assert_eq!(&content[..4], b"aaa\0");
+    assert!(content[3..].iter().all(|&b| b == 0));
+    assert_eq!(fs.metadata("docs")?.size, 9000);
+
+    fs.truncate("docs/big", 0)?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 2);
+    fs.truncate("docs/big", 2)?;
+    assert_eq!(fs.read("docs/big")?, b"\0\0");
+
+    // writing nothing past the end doesn't grow the file over the old content
+    fs.write_at("docs/big", 0, &[b'a'; 4000])?;
+    fs.truncate("docs/big", 10)?;
+    fs.write_at("docs/big", 50_000, b"")?;
+    assert_eq!(fs.metadata("docs/big")?.size, 10);
+    fs.truncate("docs/big", 4000)?;
+    assert!(fs.read("docs/big")?[10..].iter().all(|&b| b == 0));
+    fs.truncate("docs/big", 2)?;
+
+    // no file grows past what the volume can hold
+    for err in [
+        fs.truncate("docs/big", u64::MAX).unwrap_err(),
+        fs.write_at("docs/big", u64::MAX - 1, b"ab").unwrap_err(),
+        fs.write_at("docs/big", MAX_FILE_SIZE, b"a").unwrap_err(),
+    ] {
+        assert!(matches!(err.downcast_ref(), Some(FileError::FileTooLarge)));
+    }
+    fs.truncate("docs/big", MAX_FILE_SIZE)?;
+    assert_eq!(fs.read("docs/big")?.len() as u64, MAX_FILE_SIZE);
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 2);
+    let err = fs.append_bytes("docs/big", b"a").unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::FileTooLarge)));
+    fs.truncate("docs/big", 2)?;
+
+    assert!(fs.truncate("docs", 0).is_err());
+    fs.change_permissions("alias", "444")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    let err = fs.truncate("alias", 0).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToWrite(_))
+    ));
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_sparse_files() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("images")?;
+    fs.create_file_with_content("images/disk", "boot")?;
+    let used = fs.volume_usage()?.used_blocks;
+
+    // writing far past the end only allocates the written block
+    let offset = 100 * 4096 + 10;
+    fs.write_at("images/disk", offset, b"end")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used + 1);
+    let info = fs.metadata("images/disk")?;
+    assert_eq!(info.size, offset + 3);
+    assert_eq!(info.blocks, 2);
+    assert_eq!(fs.read_range("images/disk", 0, 6)?, b"boot  ");
+    assert!(fs
+        .read_range("images/disk", 4096, 50 * 4096)?
+        .iter()
+        .all(|&b| b == 0));
+    assert_eq!(fs.read_range("images/disk", offset - 2, 10)?, b"  end");
+
+    // writing into a hole splits it
+    fs.write_at("images/disk", 50 * 4096 - 2, b"midway")?;
+    assert_eq!(fs.metadata("images/disk")?.blocks, 4);
+    assert_eq!(fs.read_range("images/disk", 50 * 4096 - 3, 8)?, b" midway ");
+    assert_eq!(fs.read_range("images/disk", offset, 3)?, b"end");
+
+    // the holes survive reopening the volume
+    let content = fs.read("images/disk")?;
+    let mut fs2 = FileSystem::new(Box::new(StdIOHandler))?;
+    assert_eq!(fs2.read("images/disk")?, content);
+
+    // du tells the allocated size apart from the logical size
+    let usage = fs.dir_usage("images")?;
+    assert_eq!(usage.bytes, offset + 3);
+    assert_eq!(usage.blocks, 5);
+    assert_eq!(usage.allocated_bytes(), 5 * 4096);
+
+    // whole blocks of zeros in written content are left out as holes
+    let mut bytes = vec![0u8; 5 * 4096];
+    bytes[..2].copy_from_slice(b"hi");
+    bytes[4 * 4096] = 1;
+    fs.create_file_with_bytes("images/copy", &bytes)?;
+    assert_eq!(fs.metadata("images/copy")?.blocks, 2);
+    assert_eq!(fs.read("images/copy")?, bytes);
+
+    // shrinking into a hole frees the blocks behind it
+    fs.truncate("images/disk", 10 * 4096)?;
+    assert_eq!(fs.metadata("images/disk")?.blocks, 1);
+    fs.truncate("images/disk", 200 * 4096)?;
+    assert_eq!(fs.metadata("images/disk")?.blocks, 1);
+    assert_eq!(fs.read_range("images/disk", 150 * 4096, 4)?, b"    ");
+
+    fs.delete_file("images/disk")?;
+    fs.delete_file("images/copy")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 1);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_sparse_files_without_hole_records() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("wide", "a")?;
+    fs.write_at("wide", 100 * 4096, b"b")?;
+
+    // every other block of a second file takes up the remaining hole records
+    fs.create_file_with_content("comb", "c")?;
+    for i in 1..MAX_HOLES as u64 {
+        fs.write_at("comb", 2 * i * 4096, b"c")?;
+    }
+    assert_eq!(fs.metadata("comb")?.blocks, MAX_HOLES as u64);
+
+    // a gap past the end is filled with zero blocks
+    let used = fs.volume_usage()?.used_blocks;
+    let offset = 2 * MAX_HOLES as u64 * 4096 + 10 * 4096;
+    fs.write_at("comb", offset, b"d")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used + 12);
+    assert_eq!(
+        fs.read_range("comb", offset - 4096, 4097)?,
+        [&[0; 4096][..], b"d"].concat()
+    );
+
+    // splitting a hole keeps the record for the part behind the new block
+    fs.write_at("wide", 50 * 4096, b"e")?;
+    assert_eq!(fs.metadata("wide")?.blocks, 52);
+    let content = fs.read("wide")?;
+    assert_eq!(content.len(), 100 * 4096 + 1);
+    assert_eq!(
+        (content[0], content[50 * 4096], content[100 * 4096]),
+        (b'a', b'e', b'b')
+    );
+    assert_eq!(content.iter().filter(|&&b| b != 0).count(), 3);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::io::{self, Read};
+
+use crate::errors::{FSError, FileError};
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_copy_directory_tree() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("src")?;
+    fs.create_dir("src/sub")?;
+    fs.create_dir("src/sub/deep")?;
+    let big = "x".repeat(10_000);
+    fs.create_file_with_content("src/big", &big)?;
+    fs.create_file_with_content("src/sub/small", "Hello")?;
+    fs.create_file_with_content("src/sub/deep/leaf", "World")?;
+    fs.create_symlink("sub/small", "src/link")?;
+    fs.change_permissions("src/sub", "750")?;
+    fs.change_permissions("src/big", "600")?;
+
+    // the source is looked up from its own parent, not the current directory
+    fs.create_dir("elsewhere")?;
+    fs.change_dir("elsewhere")?;
+    fs.copy_entry("/src", "/dst")?;
+    fs.change_dir("/")?;
+
+    let source = fs
+        .walk("src")
+        .min_depth(1)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let copy = fs
+        .walk("dst")
+        .min_depth(1)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(copy.len(), source.len());
+    for (source, copy) in source.iter().zip(&copy) {
+        assert_eq!(copy.path.replacen("/dst", "/src", 1), source.path);
+        assert_eq!(copy.info.size, source.info.size);
+        assert_eq!(copy.info.mode, source.info.mode);
+        assert_eq!(copy.info.target, source.info.target);
+        assert_ne!(copy.info.blk_num, source.info.blk_num);
+    }
+    assert_eq!(fs.read("dst/big")?, big.as_bytes());
+    assert_eq!(fs.read("dst/sub/deep/leaf")?, b"World");
+    assert_eq!(fs.read("dst/link")?, b"Hello");
+
+    let size = fs.metadata("src")?.size;
+    assert_eq!(fs.metadata("dst")?.size, size);
+
+    // the copy has data of its own
+    fs.write_at("dst/sub/small", 0, b"J")?;
+    assert_eq!(fs.read("src/sub/small")?, b"Hello");
+    fs.delete_dir("dst")?;
+    assert_eq!(fs.read("src/sub/deep/leaf")?, b"World");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_copy_into_itself() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d1/d2")?;
+
+    for dest in ["d1", "d1/d2", "d1/d2/d3"] {
+        let err = fs.copy_entry("d1", dest).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(FileError::IntoItself(_))));
+    }
+    assert_eq!(fs.read_dir("d1/d2")?.len(), 0);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_copy_out_of_space() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("src")?;
+    for i in 0..4 {
+        fs.create_file_with_content(&format!("src/f{}", i), &"y".repeat(3 * 4096))?;
+    }
+
+    // leave room for only part of the copy
+    let block_size = fs.metadata("src/f0")?.size / 3;
+    let free_blocks = fs.volume_usage()?.free_bytes() / block_size;
+    let filler = io::repeat(1).take((free_blocks - 8) * block_size);
+    fs.create_file_from_reader("filler", filler)?;
+
+    let free = fs.volume_usage()?.free_bytes();
+    let err = fs.copy_entry("src", "dst").unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FSError::NoFreeBlocks)));
+    assert_eq!(fs.volume_usage()?.free_bytes(), free);
+    assert!(fs.read_dir("/")?.all(|entry| entry.name != "dst"));
+
+    // the blocks taken by the attempt are usable again
+    fs.delete_file("filler")?;
+    fs.copy_entry("src", "dst")?;
+    assert_eq!(fs.read("dst/f3")?, "y".repeat(3 * 4096).as_bytes());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_copy_sparse_file() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("sparse", "a")?;
+    fs.write_at("sparse", 40 * 4096, b"b")?;
+    fs.write_at("sparse", 90 * 4096, b"c")?;
+
+    // the holes are copied as holes
+    let used = fs.volume_usage()?.used_blocks;
+    fs.copy_entry("sparse", "copy")?;
+    assert_eq!(fs.metadata("copy")?.blocks, 3);
+    assert_eq!(fs.volume_usage()?.used_blocks, used + 3);
+    assert_eq!(fs.read("copy")?, fs.read("sparse")?);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_copy_to_symlink_loop() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("f1", "Hello")?;
+    fs.create_symlink("loop", "loop")?;
+
+    // only a missing destination is created
+    let err = fs.copy_entry("f1", "loop").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::TooManySymlinks(_))
+    ));
+    assert_eq!(fs.read_dir("/")?.len(), 2);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use rustic_disk::traits::BlockStorage;
+use rustic_disk::Disk;
+
+use crate::dir_entry::{bucket_of, hash_name};
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_directory_grows_beyond_one_block() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    let count = FileSystem::num_entries() * 2 + 5;
+    for i in 0..count {
+        fs.create_file_with_content(format!("d1/f{}", i).as_str(), "Hello!")?;
+    }
+
+    let block = fs.traverse_dir("/d1".to_string())?;
+    assert_eq!(block.blocks.len(), 3);
+    assert_eq!(fs.count_chain_blocks(block.blk_num)?, 3);
+    assert!(block.get_entry(&format!("f{}", count - 1).into()).is_some());
+
+    // the chain is read back from disk by a fresh file system
+    let fs2 = FileSystem::new(Box::new(StdIOHandler))?;
+    let block = fs2.traverse_dir("/d1".to_string())?;
+    let filled = block.entries.iter().filter(|e| !e.name.is_empty()).count();
+    assert_eq!(filled, count);
+
+    let used = fs.volume_usage()?.used_blocks;
+    fs.delete_dir("d1")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 3 - count as u64);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_root_directory_grows() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    for i in 0..FileSystem::num_entries() + 1 {
+        fs.create_dir(format!("d{}", i).as_str())?;
+    }
+    fs.change_dir(format!("d{}", FileSystem::num_entries()).as_str())?;
+    fs.create_file_with_content("f1", "Hello!")?;
+    fs.change_dir("/")?;
+    assert_eq!(fs.curr_block.blocks.len(), 2);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_hashed_directory() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format_with(FormatOptions {
+        dir_index: DirIndex::Hashed,
+    })?;
+    fs.create_dir("d1")?;
+    let count = FileSystem::num_entries() * 4;
+    for i in 0..count {
+        fs.create_file_with_content(format!("d1/f{}", i).as_str(), "Hello!")?;
+    }
+    for i in (0..count).step_by(2) {
+        fs.delete_file(format!("d1/f{}", i).as_str())?;
+    }
+
+    // a fresh file system picks the layout up from the FAT block
+    let fs2 = FileSystem::new(Box::new(StdIOHandler))?;
+    let block = fs2.traverse_dir("/d1".to_string())?;
+    assert_eq!(block.index, DirIndex::Hashed);
+    assert!(block.blocks.len() >= 4);
+    for i in 0..count {
+        let entry = block.get_entry(&format!("f{}", i).into());
+        assert_eq!(entry.is_some(), i % 2 == 1);
+    }
+
+    let names = block
+        .iter_sorted()
+        .map(|entry| entry.name.to_string())
+        .collect::<Vec<_>>();
+    let mut expected = names.clone();
+    expected.sort();
+    assert_eq!(names, expected);
+    assert_eq!(names.len(), count / 2);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_hashed_directory_with_colliding_names() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format_with(FormatOptions {
+        dir_index: DirIndex::Hashed,
+    })?;
+    fs.create_dir("d1")?;
+
+    // names that land in the same bucket for every block count up to 9
+    let names = (0..)
+        .map(|i| format!("f{}", i))
+        .filter(|name| hash_name(&name.as_str().into()).is_multiple_of(2520))
+        .take(FileSystem::num_entries() + 1)
+        .collect::<Vec<_>>();
+    for name in &names[1..] {
+        fs.create_file_with_content(&format!("d1/{}", name), "Hello!")?;
+    }
+
+    let free = fs.volume_usage()?.free_bytes();
+    let err = fs
+        .create_file_with_content(&format!("d1/{}", names[0]), "Hello!")
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::DirectoryFull)));
+    assert_eq!(fs.volume_usage()?.free_bytes(), free);
+
+    let block = fs.traverse_dir("/d1".to_string())?;
+    assert_eq!(fs.count_chain_blocks(block.blk_num)?, 1);
+    assert_eq!(block.iter().count(), names.len() - 1);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_hashed_lookups_read_one_bucket() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format_with(FormatOptions {
+        dir_index: DirIndex::Hashed,
+    })?;
+    fs.create_dir("d1")?;
+    let count = FileSystem::num_entries() * 4;
+    for i in 0..count {
+        fs.create_file_with_content(format!("d1/f{}", i).as_str(), "Hello!")?;
+    }
+    fs.create_dir("d1/sub")?;
+    fs.create_file_with_content("d1/sub/leaf", "Hello!")?;
+
+    // every block of the directory but the bucket of `sub` becomes unreadable
+    let dir = fs.traverse_dir("/d1".to_string())?;
+    let num_buckets = dir.blocks.len();
+    assert!(num_buckets >= 4);
+    let bucket = fs.read_dir_bucket(&dir.parent_entry, &"sub".into())?;
+    assert_eq!(bucket.bucket, Some(bucket_of(&"sub".into(), num_buckets)));
+    assert_eq!(bucket.blocks.len(), 1);
+    for &blk in dir.blocks.iter().filter(|&&blk| blk != bucket.blocks[0]) {
+        fs.disk.write_raw_data(blk as usize, &vec![0xFF; Disk::BLOCK_SIZE])?;
+    }
+    assert!(fs.read_dir("d1").is_err());
+
+    // looking up, adding and removing names of that bucket still works
+    assert_eq!(fs.read("d1/sub/leaf")?, b"Hello!");
+    fs.create_file_with_content("d1/sub/new", "World!")?;
+    assert_eq!(fs.metadata("d1/sub")?.size, 12);
+    let same_bucket = (0..count)
+        .map(|i| format!("f{}", i))
+        .find(|name| bucket_of(&name.as_str().into(), num_buckets) == bucket.bucket.unwrap())
+        .unwrap();
+    fs.delete_file(&format!("d1/{}", same_bucket))?;
+    assert!(fs.metadata(&format!("d1/{}", same_bucket)).is_err());
+    fs.create_file_with_content(&format!("d1/{}", same_bucket), "Again")?;
+    assert_eq!(fs.read(&format!("d1/{}", same_bucket))?, b"Again");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_long_names() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+
+    // multi-byte characters straddle the 55 byte boundary of a slot
+    let long_dir = "ö".repeat(60);
+    let long_file = format!("{}.txt", "filnamn-åäö-".repeat(15));
+    fs.create_dir(&long_dir)?;
+    fs.create_file_with_content(format!("{}/{}", long_dir, long_file).as_str(), "Hello!")?;
+    fs.create_file_with_content("short", "Hello!")?;
+
+    let root = fs.traverse_dir("/".to_string())?;
+    assert!(root.get_entry(&long_dir.clone().into()).is_some());
+    assert!(root.get_entry(&"short".into()).is_some());
+
+    // the names survive a round trip through the disk
+    let fs2 = FileSystem::new(Box::new(StdIOHandler))?;
+    let block = fs2.traverse_dir(format!("/{}", long_dir))?;
+    let entry = block.get_entry(&long_file.clone().into()).unwrap();
+    assert_eq!(entry.name.to_string(), long_file);
+    assert_eq!(block.iter().count(), 1);
+
+    // deleting an entry frees its continuation slots
+    fs.delete_file(format!("{}/{}", long_dir, long_file).as_str())?;
+    let block = fs.traverse_dir(format!("/{}", long_dir))?;
+    assert!(block.entries.iter().all(|entry| entry.is_free()));
+
+    let too_long = "a".repeat(256);
+    assert!(fs.create_file_with_content(&too_long, "Hello!").is_err());
+    assert!(fs.create_dir(&too_long).is_err());
+    assert!(fs.move_entry("short", &too_long).is_err());
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_structured_queries() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("docs")?;
+    fs.create_file_with_content("docs/notes", "Hello")?;
+    fs.create_dir("docs/sub")?;
+    fs.create_symlink("notes", "docs/link")?;
+    assert_eq!(fs.cwd(), "/");
+
+    let entries = fs.read_dir("docs")?.collect::<Vec<_>>();
+    let names = entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["link", "notes", "sub"]);
+    assert!(entries[0].is_symlink());
+    assert_eq!(entries[0].target.as_deref(), Some("notes"));
+    assert!(entries[1].is_file());
+    assert_eq!(entries[1].size, 5);
+    assert!(entries[2].is_dir());
+
+    // reading through the link gives the content of the file
+    assert_eq!(fs.read("docs/link")?, b"Hello");
+    let info = fs.metadata("docs/link")?;
+    assert_eq!(info.file_type, FileType::Symlink);
+    assert_eq!(fs.metadata("docs/notes")?.permissions().to_string(), "rw-r--r--");
+
+    fs.change_dir("docs/sub")?;
+    assert_eq!(fs.cwd(), "/docs/sub");
+    assert_eq!(fs.read_dir(".")?.len(), 0);
+    assert!(fs.read_dir("../notes").is_err());
+    assert!(fs.read("..").is_err());
+
+    // a link whose target can't be read is still listed
+    let link = fs.metadata("/docs/link")?;
+    fs.disk.write_raw_data(link.blk_num as usize, &[0xff, 0xfe])?;
+    let entries = fs.read_dir("/docs")?.collect::<Vec<_>>();
+    assert_eq!(entries[0].name, "link");
+    assert_eq!(entries[0].target, None);
+    fs.change_dir("/docs")?;
+    fs.list_dir()?;
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_walk() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("a")?;
+    fs.create_dir("a/b")?;
+    fs.create_file_with_content("a/b/deep", "x")?;
+    fs.create_file_with_content("a/file", "x")?;
+    fs.create_dir("a/skip")?;
+    fs.create_file_with_content("a/skip/hidden", "x")?;
+    fs.create_symlink("/a", "a/loop")?;
+
+    let paths = |walk: Walk| -> anyhow::Result<Vec<String>> {
+        walk.map(|entry| entry.map(|e| e.path)).collect()
+    };
+
+    // directories come before their contents and links are not followed
+    assert_eq!(
+        paths(fs.walk("a"))?,
+        ["/a", "/a/b", "/a/b/deep", "/a/file", "/a/loop", "/a/skip", "/a/skip/hidden"]
+    );
+    assert_eq!(
+        paths(fs.walk("a").contents_first(true).filter_entry(|e| e.info.name != "skip"))?,
+        ["/a/b/deep", "/a/b", "/a/file", "/a/loop", "/a"]
+    );
+    assert_eq!(
+        paths(fs.walk("/").min_depth(2).max_depth(2))?,
+        ["/a/b", "/a/file", "/a/loop", "/a/skip"]
+    );
+    let depths = fs
+        .walk("a/loop")
+        .max_depth(1)
+        .map(|entry| entry.map(|e| e.depth))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(depths, [0, 1, 1, 1, 1]);
+
+    // a directory that can't be read is yielded before its error
+    fs.change_permissions("a/skip", "300")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    let results = fs.walk("a/skip").collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().path, "/a/skip");
+    assert!(results[1].is_err());
+    assert!(fs.walk("missing").next().unwrap().is_err());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
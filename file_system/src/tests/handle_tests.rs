@@ -0,0 +1,153 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::errors::FileError;
+use crate::prelude::*;
+use crate::FileSystem;
+
+/// Reads the whole content of the file at `path` through a new handle.
+fn read_all(fs: &mut FileSystem, path: &str) -> anyhow::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    fs.open(path, OpenOptions::new().read(true))?
+        .read_to_end(&mut content)?;
+    Ok(content)
+}
+
+#[test]
+fn test_handle_read_write_seek() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("notes", "Hello World")?;
+
+    {
+        let mut file = fs.open("notes", OpenOptions::new().read(true).write(true))?;
+        let mut word = [0u8; 5];
+        file.read_exact(&mut word)?;
+        assert_eq!(&word, b"Hello");
+
+        file.seek(SeekFrom::Start(6))?;
+        file.write_all(b"Rust!")?;
+        assert_eq!(file.seek(SeekFrom::End(-1))?, 10);
+        assert!(file.seek(SeekFrom::Current(-20)).is_err());
+
+        // writing past the end fills the gap with zeros
+        file.seek(SeekFrom::End(2))?;
+        file.write_all(b"!")?;
+        file.flush()?;
+    }
+    assert_eq!(read_all(&mut fs, "notes")?, b"Hello Rust!\0\0!");
+
+    {
+        let mut file = fs.open("notes", OpenOptions::new().write(true))?;
+        file.set_len(5)?;
+        let mut buf = Vec::new();
+        assert!(file.read_to_end(&mut buf).is_err());
+    }
+    assert_eq!(read_all(&mut fs, "notes")?, b"Hello");
+
+    let mut file = fs.open("notes", OpenOptions::new().append(true))?;
+    let word = "again";
+    write!(file, ", {}", word)?;
+    drop(file);
+    assert_eq!(read_all(&mut fs, "notes")?, b"Hello, again");
+
+    // positions and sizes are limited to what the volume can hold
+    let mut file = fs.open("notes", OpenOptions::new().write(true))?;
+    assert!(file.seek(SeekFrom::Start(u64::MAX)).is_err());
+    file.seek(SeekFrom::Start(MAX_FILE_SIZE))?;
+    let err = file.write(b"!").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::FileTooLarge);
+    let err = file.set_len(u64::MAX).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::FileTooLarge)));
+    drop(file);
+    assert_eq!(read_all(&mut fs, "notes")?, b"Hello, again");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_handle_open_options() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("docs")?;
+
+    let err = fs
+        .open("docs/new", OpenOptions::new().read(true))
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::FileNotFound)));
+    assert!(fs.open("docs", OpenOptions::new().read(true)).is_err());
+    assert!(fs.open("docs/new", OpenOptions::new()).is_err());
+    assert!(fs
+        .open("docs/new", OpenOptions::new().read(true).create(true))
+        .is_err());
+
+    // larger than a block, so the content is chained over several blocks
+    let big = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs.open("docs/new", OpenOptions::new().write(true).create_new(true))?
+        .write_all(&big)?;
+    assert_eq!(read_all(&mut fs, "docs/new")?, big);
+    let err = fs
+        .open("docs/new", OpenOptions::new().write(true).create_new(true))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::FileAlreadyExists)
+    ));
+
+    fs.open("docs/new", OpenOptions::new().write(true).truncate(true))?
+        .write_all(b"short")?;
+    assert_eq!(read_all(&mut fs, "docs/new")?, b"short");
+
+    // writes through a hard link show up under every name
+    fs.create_hard_link("docs/new", "alias")?;
+    fs.open("alias", OpenOptions::new().append(true))?
+        .write_all(b" and more")?;
+    assert_eq!(read_all(&mut fs, "docs/new")?, b"short and more");
+
+    // reading needs read and writing write permission on the file
+    fs.change_permissions("docs/new", "644")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    assert!(fs.open("docs/new", OpenOptions::new().read(true)).is_ok());
+    let err = fs
+        .open("docs/new", OpenOptions::new().write(true))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToWrite(_))
+    ));
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_handle_flush_out_of_space() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let content = "x".repeat(2 * 4096);
+    fs.create_file_with_content("notes", &content)?;
+
+    // leave room for a single block
+    let free_blocks = fs.volume_usage()?.free_bytes() / 4096;
+    let filler = std::io::repeat(1).take((free_blocks - 1) * 4096);
+    fs.create_file_from_reader("filler", filler)?;
+    let free = fs.volume_usage()?.free_bytes();
+
+    // the old content stays until the new one is written completely
+    let mut file = fs.open("notes", OpenOptions::new().write(true))?;
+    file.write_all("y".repeat(4 * 4096).as_bytes())?;
+    assert!(file.flush().is_err());
+    drop(file);
+    assert_eq!(fs.read("notes")?, content.as_bytes());
+    assert_eq!(fs.volume_usage()?.free_bytes(), free);
+
+    fs.delete_file("filler")?;
+    let mut file = fs.open("notes", OpenOptions::new().write(true))?;
+    file.write_all(b"Hello")?;
+    file.flush()?;
+    drop(file);
+    assert_eq!(&fs.read("notes")?[..6], b"Hellox");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
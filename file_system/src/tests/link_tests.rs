@@ -0,0 +1,129 @@
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_symlinks() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("shared")?;
+    fs.create_dir("shared/scripts")?;
+    fs.create_file_with_content("shared/scripts/hello.py", "print('hello')")?;
+    fs.create_dir("home")?;
+
+    fs.create_symlink("/shared/scripts", "home/scripts")?;
+    fs.create_symlink("scripts/hello.py", "home/hello")?;
+    assert_eq!(fs.read_link("home/scripts")?, "/shared/scripts");
+    assert_eq!(fs.read_link("/home/hello")?, "scripts/hello.py");
+    assert!(fs.read_link("home").is_err());
+
+    // links are followed in the middle and at the end of a path
+    assert_eq!(
+        fs.resolve_path("/home/hello", true)?,
+        "/shared/scripts/hello.py"
+    );
+    assert_eq!(fs.resolve_path("/home/hello", false)?, "/home/hello");
+    assert!(fs.read_file("home/scripts/hello.py").is_ok());
+    assert!(fs.read_file("home/hello").is_ok());
+    fs.create_file_with_content("home/scripts/new.py", "print('new')")?;
+    assert!(fs
+        .traverse_dir("/shared/scripts".to_string())?
+        .get_entry(&"new.py".into())
+        .is_some());
+
+    fs.change_dir("home/scripts")?;
+    assert_eq!(fs.curr_block.path, "/shared/scripts");
+    fs.change_dir("/")?;
+
+    // removing a link leaves the target alone
+    fs.remove_entry("home/hello")?;
+    assert!(fs.read_link("home/hello").is_err());
+    assert!(fs.read_file("shared/scripts/hello.py").is_ok());
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_symlink_loops() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_symlink("b", "a")?;
+    fs.create_symlink("a", "b")?;
+    fs.create_symlink("self", "self")?;
+
+    for path in ["a", "b", "self"] {
+        let err = fs.read_file(path).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FileError>(),
+            Some(FileError::TooManySymlinks(_))
+        ));
+    }
+    assert!(fs.change_dir("a").is_err());
+    assert!(fs.traverse_dir("/self/x".to_string()).is_err());
+
+    // a dangling link can still be read and removed
+    fs.create_symlink("/missing", "dangling")?;
+    assert_eq!(fs.read_link("dangling")?, "/missing");
+    assert!(fs.read_file("dangling").is_err());
+    fs.remove_entry("dangling")?;
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_hard_links() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("f1", "Hello!")?;
+    fs.create_hard_link("f1", "d1/f2")?;
+    fs.create_hard_link("d1/f2", "f3")?;
+    assert!(fs.create_hard_link("d1", "d2").is_err());
+    assert!(fs.create_hard_link("f1", "f3").is_err());
+
+    let root = fs.traverse_dir("/".to_string())?;
+    let d1 = fs.traverse_dir("/d1".to_string())?;
+    let f1 = root.get_entry(&"f1".into()).unwrap().clone();
+    let f2 = d1.get_entry(&"f2".into()).unwrap();
+    assert_eq!(f1.blk_num, f2.blk_num);
+    assert_eq!(f1.link_count, 3);
+    assert_eq!(f2.link_count, 3);
+    assert_eq!(root.parent_entry.size, 0);
+    assert_eq!(root.get_entry(&"d1".into()).unwrap().size, f1.size);
+
+    // appending through one name is visible through all of them
+    fs.create_file_with_content("tail", "World!")?;
+    fs.append_file("tail", "f3")?;
+    let d1 = fs.traverse_dir("/d1".to_string())?;
+    let f2 = d1.get_entry(&"f2".into()).unwrap().clone();
+    assert!(f2.size > f1.size);
+    let root = fs.traverse_dir("/".to_string())?;
+    assert_eq!(root.get_entry(&"d1".into()).unwrap().size, f2.size);
+
+    // the data survives until the last name is removed
+    fs.delete_file("f1")?;
+    fs.delete_dir("d1")?;
+    let root = fs.traverse_dir("/".to_string())?;
+    let f3 = root.get_entry(&"f3".into()).unwrap().clone();
+    assert_eq!(f3.link_count, 1);
+    assert!(fs.read_file("f3").is_ok());
+
+    let used = fs.volume_usage()?.used_blocks;
+    fs.delete_file("f3")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used - 1);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_hard_links_in_removed_directory() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let used = fs.volume_usage()?.used_blocks;
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/f1", "Hello!")?;
+    fs.create_hard_link("d1/f1", "d1/f2")?;
+    fs.delete_dir("d1")?;
+    assert_eq!(fs.volume_usage()?.used_blocks, used);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
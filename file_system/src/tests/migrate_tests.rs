@@ -0,0 +1,114 @@
+use rustic_disk::traits::BlockStorage;
+use serde_derive::Serialize;
+
+use crate::dir_entry::{DirEntry, FileType};
+use crate::fat::FatType;
+use crate::file_data::FileData;
+use crate::prelude::*;
+use crate::utils::fixed_str::FixedString;
+use crate::FileSystem;
+
+/// Directory entry as written by volumes of version 1.
+#[derive(Debug, Serialize)]
+struct DirEntryV1 {
+    name: FixedString,
+    file_type: FileType,
+    size: u64,
+    blk_num: u16,
+    access_level: u8,
+}
+
+impl From<DirEntry> for DirEntryV1 {
+    fn from(entry: DirEntry) -> Self {
+        DirEntryV1 {
+            name: entry.name,
+            file_type: entry.file_type,
+            size: entry.size,
+            blk_num: entry.blk_num,
+            access_level: (entry.mode & 0o7) as u8,
+        }
+    }
+}
+
+#[test]
+fn test_version_1_volume_is_migrated() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let long_name = "a".repeat(100);
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/f1", "Hello!")?;
+    fs.create_file_with_content(&format!("d1/{}", long_name), "Hello!")?;
+    // fill the root so that it no longer fits a single block in the new layout
+    for i in 0..FileSystem::num_entries() + 1 {
+        fs.create_file_with_content(format!("f{}", i).as_str(), "Hello!")?;
+    }
+
+    // rewrite every directory as a version 1 volume
+    let blocks = vec![
+        fs.traverse_dir("/".to_string())?,
+        fs.traverse_dir("/d1".to_string())?,
+    ];
+    for block in blocks {
+        // files held a serialized `FileData` before version 7
+        for entry in block.iter().filter(|e| e.file_type == FileType::File) {
+            fs.write_data(&FileData::from("Hello!"), entry.blk_num)?;
+        }
+        let legacy = block
+            .split_long_names()
+            .into_iter()
+            .map(DirEntryV1::from)
+            .collect::<Vec<_>>();
+        let per_block = legacy.len().div_ceil(block.blocks.len());
+        for (blk, chunk) in block.blocks.iter().zip(legacy.chunks(per_block)) {
+            fs.disk.write_block(*blk as usize, &chunk)?;
+        }
+    }
+    fs.fat.version = 1;
+    fs.disk.write_block(1, &fs.fat)?;
+
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    assert_eq!(fs.fat.version, crate::fat::FAT_VERSION);
+    let root = fs.traverse_dir("/".to_string())?;
+    assert_eq!(root.iter().count(), FileSystem::num_entries() + 2);
+    let d1 = fs.traverse_dir("/d1".to_string())?;
+    let entry = d1.get_entry(&long_name.clone().into()).unwrap();
+    assert_eq!(entry.link_count, 1);
+    // times weren't stored before, so they are unknown
+    assert_eq!(entry.created, 0);
+    assert_eq!(entry.modified, 0);
+    // the single access level applies to every class
+    assert_eq!(entry.mode, 0o444);
+    assert_eq!(entry.uid, ROOT_UID);
+    assert!(fs.read_file("d1/f1").is_ok());
+    assert!(fs.read_file(&format!("d1/{}", long_name)).is_ok());
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_version_6_contents_are_migrated() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let content = "0123456789".repeat(1000);
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/big", &content)?;
+    fs.create_hard_link("d1/big", "alias")?;
+
+    // rewrite the content as a serialized `FileData` spread over several blocks
+    let blk_num = fs.lookup_entry("/d1/big", false)?.blk_num;
+    fs.clear_file_data(blk_num)?;
+    fs.set_fat_block(blk_num, FatType::EOF)?;
+    fs.write_data(&FileData::from(content.as_str()), blk_num)?;
+    fs.fat.version = 6;
+    fs.disk.write_block(1, &fs.fat)?;
+
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    assert_eq!(fs.fat.version, crate::fat::FAT_VERSION);
+    assert_eq!(fs.lookup_entry("/d1/big", false)?.size, 10_000);
+    assert_eq!(fs.lookup_entry("/alias", false)?.size, 10_000);
+    assert_eq!(fs.lookup_entry("/d1", false)?.size, 10_000);
+    assert_eq!(fs.read_range("alias", 0, 5)?, b"01234");
+    assert_eq!(fs.read_range("d1/big", 9_995, 100)?, b"56789");
+    fs.disk.delete_disk()?;
+    Ok(())
+}
@@ -1,36 +1,68 @@
 use crate::prelude::Input;
 
+#[cfg(test)]
+mod acl_tests;
+#[cfg(test)]
+mod content_tests;
+#[cfg(test)]
+mod copy_tests;
+#[cfg(test)]
+mod dir_tests;
+#[cfg(test)]
+mod handle_tests;
+#[cfg(test)]
+mod link_tests;
+#[cfg(test)]
+mod migrate_tests;
+#[cfg(test)]
+mod move_tests;
 #[cfg(test)]
 mod path_tests;
 #[cfg(test)]
+mod permission_tests;
+#[cfg(test)]
+mod size_tests;
+#[cfg(test)]
 mod task1;
+#[cfg(test)]
 mod task2;
+#[cfg(test)]
 mod task3;
+#[cfg(test)]
+mod time_tests;
+#[cfg(test)]
+mod usage_tests;
+#[cfg(test)]
+mod user_tests;
+#[cfg(test)]
+mod xattr_tests;
 
 #[derive(Debug)]
 pub(crate) struct MockInput {
-    pub(crate) input: String,
+    pub(crate) input: Vec<u8>,
 }
 
 impl MockInput {
-    pub(crate) fn new(input: &str) -> Self {
+    pub(crate) fn new(input: impl Into<Vec<u8>>) -> Self {
         Self {
-            input: input.to_string(),
+            input: input.into(),
         }
     }
 }
 
 impl Input for MockInput {
-    fn read_lines(&mut self) -> anyhow::Result<String> {
+    fn read_data(&mut self) -> anyhow::Result<Vec<u8>> {
         Ok(self.input.clone())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use rustic_disk::Disk;
 
     use crate::dir_entry::{DirBlock, DirEntry, FileType};
+    use crate::errors::FileError;
     use crate::utils::fixed_str::FixedString;
     use crate::FileSystem;
 
@@ -42,7 +74,9 @@ mod tests {
             file_type: FileType::File,
             size: 0,
             blk_num: 0,
-            access_level: 0,
+            mode: 0,
+            link_count: 1,
+            ..Default::default()
         };
 
         let serialized = bincode::serialize(&dir_entry).expect("Failed to serialize DirEntry");
@@ -71,9 +105,11 @@ mod tests {
 
     #[test]
     fn add_entries_in_block() {
-        let mut block = DirBlock::default();
         let max_entry = DirEntry::gen_max_size_entry();
-        block.entries = vec![DirEntry::default(); FileSystem::num_entries()];
+        let mut block = DirBlock {
+            entries: vec![DirEntry::default(); FileSystem::num_entries()],
+            ..Default::default()
+        };
 
         let mut size = block.get_size();
         assert!(size <= Disk::BLOCK_SIZE, "Block exceeds single block size");
@@ -101,23 +137,33 @@ mod tests {
             FileSystem::num_entries(),
             "Block should be full"
         );
+
+        let err = block.add_entry(max_entry).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FileError>(),
+            Some(FileError::DirectoryFull)
+        ));
     }
 
     #[test]
     fn add_real_entries() {
-        let mut block = DirBlock::default();
-        block.entries = vec![DirEntry::default(); FileSystem::num_entries()];
+        let mut block = DirBlock {
+            entries: vec![DirEntry::default(); FileSystem::num_entries()],
+            ..Default::default()
+        };
 
         let mut size = block.get_size();
         assert!(size <= Disk::BLOCK_SIZE, "Block exceeds single block size");
 
         for i in 1..(FileSystem::num_entries() + 1) {
             let entry = DirEntry {
-                name: FixedString::from(format!("f{}", &i)),
+                name: FixedString::from(format!("f{}", i)),
                 file_type: FileType::File,
                 size: 20,
                 blk_num: i as u16,
-                access_level: 0,
+                mode: 0,
+                link_count: 1,
+                ..Default::default()
             };
             block.add_entry(entry).unwrap();
             let new_size = block.get_size();
@@ -131,9 +177,11 @@ mod tests {
 
     #[test]
     fn remove_entries_in_block() {
-        let mut block = DirBlock::default();
         let max_entry = DirEntry::gen_max_size_entry();
-        block.entries = vec![max_entry.clone(); FileSystem::num_entries()];
+        let mut block = DirBlock {
+            entries: vec![max_entry.clone(); FileSystem::num_entries()],
+            ..Default::default()
+        };
 
         let mut size = block.get_size();
         for _ in 0..FileSystem::num_entries() {
@@ -149,9 +197,40 @@ mod tests {
 }
 
 #[cfg(test)]
-mod generic_tests {
-    use rustic_disk::Disk;
+mod fat_tests {
+    use crate::fat::{FatType, FAT};
+    use crate::format::{DirIndex, FormatOptions};
+
+    #[test]
+    fn legacy_fat_is_readable() {
+        let legacy = vec![FatType::EOF, FatType::EOF, FatType::Taken(3), FatType::EOF, FatType::Free];
+        let serialized = bincode::serialize(&legacy).unwrap();
+        let fat: FAT = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(fat.len(), legacy.len());
+        assert_eq!(fat[2], FatType::Taken(3));
+        assert_eq!(fat.options, FormatOptions::default());
+    }
 
+    #[test]
+    fn packed_fat_round_trips() {
+        let mut fat = FAT::with_options(FormatOptions {
+            dir_index: DirIndex::Hashed,
+        });
+        for blk in 2..fat.len() - 1 {
+            fat[blk] = FatType::Taken(blk as u16 + 1);
+        }
+        let serialized = bincode::serialize(&fat).unwrap();
+        assert!(serialized.len() <= rustic_disk::Disk::BLOCK_SIZE);
+
+        let read: FAT = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(read.options.dir_index, DirIndex::Hashed);
+        assert_eq!(read[0], FatType::Free);
+        assert_eq!(read[5], FatType::Taken(6));
+    }
+}
+
+#[cfg(test)]
+mod generic_tests {
     use crate::prelude::*;
     use crate::FileSystem;
 
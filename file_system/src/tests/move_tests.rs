@@ -0,0 +1,159 @@
+use std::io::{self, Read};
+
+use crate::errors::{FSError, FileError};
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_move_directory_across_parents() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("a")?;
+    fs.create_dir("a/b")?;
+    fs.create_dir("a/b/tree")?;
+    fs.create_dir("a/b/tree/sub")?;
+    fs.create_file_with_content("a/b/tree/sub/leaf", "Hello, World!")?;
+    fs.create_file_with_content("a/b/other", "Hello")?;
+    fs.create_dir("c")?;
+    fs.create_dir("c/d")?;
+    let tree = fs.metadata("a/b/tree")?.size;
+    let other = fs.metadata("a/b/other")?.size;
+
+    // the current directory moves along with the tree
+    fs.change_dir("a/b/tree/sub")?;
+    fs.move_entry("/a/b/tree", "/c/d/moved")?;
+    assert_eq!(fs.cwd(), "/c/d/moved/sub");
+    assert_eq!(fs.read("leaf")?, b"Hello, World!");
+    fs.change_dir("/")?;
+
+    assert!(fs.metadata("a/b/tree").is_err());
+    assert_eq!(fs.metadata("a")?.size, other);
+    assert_eq!(fs.metadata("a/b")?.size, other);
+    assert_eq!(fs.metadata("c")?.size, tree);
+    assert_eq!(fs.metadata("c/d")?.size, tree);
+    assert_eq!(fs.metadata("c/d/moved")?.size, tree);
+
+    // a rename within the same directory keeps the sizes
+    fs.move_entry("c/d/moved", "c/d/renamed")?;
+    assert_eq!(fs.metadata("c")?.size, tree);
+    assert_eq!(fs.read("c/d/renamed/sub/leaf")?, b"Hello, World!");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_move_into_itself() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d1/d2")?;
+    fs.create_file_with_content("d1/d2/f1", "Hello")?;
+
+    for dest in ["d1", "d1/d2", "d1/d2/d3", "/d1/d2/../d2/d3"] {
+        let err = fs.move_entry("d1", dest).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(FileError::IntoItself(_))));
+    }
+    let err = fs.move_entry_overwrite("d1", "d1/d2/f1").unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::IntoItself(_))));
+    assert_eq!(fs.read("d1/d2/f1")?, b"Hello");
+    assert_eq!(fs.metadata("d1")?.size, 5);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_move_overwrite() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d2")?;
+    fs.create_file_with_content("d1/new", "New content")?;
+    fs.create_file_with_content("d2/old", &"o".repeat(10_000))?;
+
+    let err = fs.move_entry("d1/new", "d2/old").unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FileError::FileExists(_))));
+
+    // the replaced file is freed and the sizes only count the new one
+    let free = fs.volume_usage()?.free_bytes();
+    fs.move_entry_overwrite("d1/new", "d2/old")?;
+    assert_eq!(fs.read("d2/old")?, b"New content");
+    assert!(fs.metadata("d1/new").is_err());
+    assert_eq!(fs.volume_usage()?.free_bytes(), free + 3 * 4096);
+    assert_eq!(fs.metadata("d1")?.size, 0);
+    assert_eq!(fs.metadata("d2")?.size, 11);
+
+    // another name of the same file is left alone
+    fs.create_hard_link("d2/old", "d2/link")?;
+    fs.move_entry_overwrite("d2/link", "d2/old")?;
+    assert_eq!(fs.metadata("d2/link")?.link_count, 2);
+    assert_eq!(fs.read("d2/old")?, b"New content");
+
+    // a file can't replace a directory and a directory only an empty one
+    fs.create_dir("d1/old")?;
+    let err = fs.move_entry_overwrite("d2/old", "d1").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::FileIsDirectory)
+    ));
+    fs.create_dir("d2/dir")?;
+    let err = fs.move_entry_overwrite("d2/dir", "d2/old").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NotADirectory(_))
+    ));
+    fs.create_dir("d1/dir")?;
+    fs.create_file_with_content("d1/dir/f1", "Hello")?;
+    fs.create_file_with_content("d2/dir/f2", "World!")?;
+    let err = fs.move_entry_overwrite("d2/dir", "d1").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::DirectoryNotEmpty(_))
+    ));
+
+    fs.delete_file("d1/dir/f1")?;
+    fs.move_entry_overwrite("d2/dir", "d1")?;
+    assert_eq!(fs.read("d1/dir/f2")?, b"World!");
+    assert_eq!(fs.metadata("d1")?.size, 6);
+    // both names of the hard link count
+    assert_eq!(fs.metadata("d2")?.size, 22);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_move_out_of_space() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("src")?;
+    fs.create_file_with_content("src/f1", "Hello")?;
+    fs.create_dir("full")?;
+    for i in 0..FileSystem::num_entries() {
+        fs.create_file_with_content(&format!("full/f{}", i + 2), "")?;
+    }
+
+    // the destination directory has to grow but there is no block left
+    let block_size = 4096;
+    let free_blocks = fs.volume_usage()?.free_bytes() / block_size;
+    let filler = io::repeat(1).take(free_blocks * block_size);
+    fs.create_file_from_reader("src/filler", filler)?;
+    assert_eq!(fs.volume_usage()?.free_bytes(), 0);
+
+    let err = fs.move_entry("src/f1", "full").unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FSError::NoFreeBlocks)));
+    assert_eq!(fs.read("src/f1")?, b"Hello");
+    assert!(fs.metadata("full/f1").is_err());
+    assert_eq!(fs.read_dir("full")?.len(), FileSystem::num_entries());
+    assert_eq!(fs.volume_usage()?.free_bytes(), 0);
+
+    // both directories are still usable once there is room again
+    fs.delete_file("src/filler")?;
+    fs.move_entry("src/f1", "full")?;
+    assert_eq!(fs.read("full/f1")?, b"Hello");
+    assert_eq!(fs.read_dir("src")?.len(), 0);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use crate::prelude::*;
+use crate::errors::FileError;
+use crate::FileSystem;
+
+#[test]
+fn test_owner_group_other() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("shared")?;
+    fs.create_file_with_content("shared/notes", "Hello")?;
+
+    let entry = fs.lookup_entry("/shared/notes", false)?;
+    assert_eq!(
+        (entry.mode, entry.uid, entry.gid),
+        (0o644, ROOT_UID, ROOT_GID)
+    );
+    assert_eq!(fs.lookup_entry("/shared", false)?.mode, 0o755);
+
+    // other may read but not write into a directory of the superuser
+    fs.set_credentials(Credentials::new(1000, 1000));
+    assert!(fs.read_file("shared/notes").is_ok());
+    assert!(fs.create_file_with_content("shared/mine", "Hello").is_err());
+
+    fs.set_credentials(Credentials::root());
+    fs.change_permissions("shared/notes", "640")?;
+    fs.change_owner("shared", Some(1000), Some(1000))?;
+    fs.change_owner("shared/notes", None, Some(1000))?;
+
+    // the group bits apply to members of the group, the owner bits to the owner
+    fs.set_credentials(Credentials::new(1001, 1000));
+    assert!(fs.read_file("shared/notes").is_ok());
+    fs.set_credentials(Credentials::new(1002, 1002));
+    assert!(fs.read_file("shared/notes").is_err());
+    fs.set_credentials(Credentials::new(1000, 1000));
+    fs.create_file_with_content("shared/mine", "Hello")?;
+    let entry = fs.lookup_entry("/shared/mine", false)?;
+    assert_eq!((entry.uid, entry.gid), (1000, 1000));
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_chmod_and_chown() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("f1", "Hello")?;
+    fs.create_hard_link("f1", "f2")?;
+
+    for mode in ["8", "7", "1234", "abc", "778"] {
+        assert!(fs.change_permissions("f1", mode).is_err());
+    }
+    // every name of the file shares its mode and owner
+    fs.change_permissions("f1", "600")?;
+    fs.change_owner("f1", Some(1000), Some(100))?;
+    let entry = fs.lookup_entry("/f2", false)?;
+    assert_eq!((entry.mode, entry.uid, entry.gid), (0o600, 1000, 100));
+
+    // only the owner may change the mode and only the superuser gives files away
+    fs.set_credentials(Credentials::new(1001, 100));
+    assert!(fs.change_permissions("f1", "666").is_err());
+    assert!(fs.change_owner("f1", None, Some(1001)).is_err());
+    fs.set_credentials(Credentials::new(1000, 100));
+    fs.change_permissions("f1", "640")?;
+    fs.change_owner("f1", None, Some(1000))?;
+    assert!(fs.change_owner("f1", Some(1001), None).is_err());
+    assert_eq!(fs.lookup_entry("/f1", false)?.gid, 1000);
+
+    // the root has no stored entry to change
+    fs.set_credentials(Credentials::root());
+    for err in [
+        fs.change_permissions("/", "700").unwrap_err(),
+        fs.change_permissions_recursive("/", "700").unwrap_err(),
+        fs.change_owner("/", Some(1000), None).unwrap_err(),
+    ] {
+        assert!(matches!(err.downcast_ref(), Some(FileError::NotPermitted(_))));
+    }
+    assert_eq!(fs.lookup_entry("/f1", false)?.mode, 0o640);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_search_permission() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("a")?;
+    fs.create_dir("a/b")?;
+    fs.create_file_with_content("a/b/notes", "Hello")?;
+    fs.create_dir("pub")?;
+    fs.change_permissions("pub", "777")?;
+    fs.create_hard_link("a/b/notes", "pub/notes")?;
+    fs.change_permissions("pub/notes", "666")?;
+    fs.change_owner("a", Some(1000), Some(1000))?;
+
+    // without the execute bit nothing below the directory can be reached
+    fs.change_permissions("a", "744")?;
+    fs.set_credentials(Credentials::new(1001, 1001));
+    let err = fs.read_file("a/b/notes").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToExecute(_))
+    ));
+    assert!(fs.change_dir("a").is_err());
+    assert!(fs.change_dir("a/b").is_err());
+    assert!(fs.create_file_with_content("a/b/mine", "Hello").is_err());
+    assert!(fs.lookup_entry("/a/b", false).is_err());
+
+    // other names of a file are still kept up to date
+    fs.create_file_with_content("pub/more", "World")?;
+    fs.append_file("pub/more", "pub/notes")?;
+    fs.set_credentials(Credentials::root());
+    assert_eq!(
+        fs.lookup_entry("/a/b/notes", false)?.size,
+        fs.lookup_entry("/pub/notes", false)?.size
+    );
+
+    // the execute bit alone allows entering but not listing
+    fs.change_permissions("a", "711")?;
+    fs.set_credentials(Credentials::new(1001, 1001));
+    assert!(fs.read_file("a/b/notes").is_ok());
+    fs.change_dir("a")?;
+    let err = fs.list_dir().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToRead(_))
+    ));
+    fs.change_dir("/pub")?;
+    assert!(fs.list_dir().is_ok());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_permissions_parse_and_display() -> anyhow::Result<()> {
+    let permissions = "755".parse::<Permissions>()?;
+    assert_eq!(permissions.bits(), 0o755);
+    assert_eq!(permissions.to_string(), "rwxr-xr-x");
+    assert_eq!("rw-r-----".parse::<Permissions>()?.bits(), 0o640);
+    for mode in ["75", "758", "rwxr-xr-", "rwxr-xr-w"] {
+        assert!(mode.parse::<Permissions>().is_err());
+    }
+
+    let current = Permissions::from_bits_truncate(0o644);
+    let apply = |change: &str| -> anyhow::Result<u16> {
+        Ok(change.parse::<ModeChange>()?.apply(current).bits())
+    };
+    assert_eq!(apply("700")?, 0o700);
+    assert_eq!(apply("u+x,g-r")?, 0o704);
+    assert_eq!(apply("+x")?, 0o755);
+    assert_eq!(apply("go=w")?, 0o622);
+    assert_eq!(apply("a-rw,u+r-x")?, 0o400);
+    for change in ["u", "u*x", "x+u", "u+x,", "ug+rz"] {
+        assert!(change.parse::<ModeChange>().is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_recursive_chmod() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("a")?;
+    fs.create_dir("a/b")?;
+    fs.create_file_with_content("a/b/f1", "Hello")?;
+    fs.create_file_with_content("a/f2", "Hello")?;
+
+    // a directory keeps its children untouched unless asked to recurse
+    fs.change_permissions("a", "700")?;
+    assert_eq!(fs.lookup_entry("/a/f2", false)?.mode, 0o644);
+    assert_eq!(fs.lookup_entry("/a/b", false)?.mode, 0o755);
+
+    fs.change_permissions_recursive("a", "go-rx,u+w")?;
+    assert_eq!(fs.lookup_entry("/a", false)?.mode, 0o700);
+    assert_eq!(fs.lookup_entry("/a/b", false)?.mode, 0o700);
+    assert_eq!(fs.lookup_entry("/a/b/f1", false)?.mode, 0o600);
+    assert_eq!(fs.lookup_entry("/a/f2", false)?.mode, 0o600);
+
+    // an owner may lock themselves out of a whole tree
+    fs.change_owner("a", Some(1000), Some(1000))?;
+    fs.change_owner("a/b", Some(1000), Some(1000))?;
+    fs.change_owner("a/b/f1", Some(1000), Some(1000))?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    // a/f2 still belongs to the superuser, so nothing changes
+    assert!(fs.change_permissions_recursive("a", "000").is_err());
+    assert_eq!(fs.lookup_entry("/a/b/f1", false)?.mode, 0o600);
+    fs.change_permissions_recursive("a/b", "a-rwx")?;
+    assert_eq!(fs.lookup_entry("/a/b", false)?.mode, 0o000);
+    fs.set_credentials(Credentials::root());
+    assert_eq!(fs.lookup_entry("/a/b/f1", false)?.mode, 0o000);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_recursive_delete_permission() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("tmp")?;
+    fs.change_permissions("tmp", "777")?;
+    fs.create_dir("tmp/private")?;
+    fs.change_permissions("tmp/private", "700")?;
+    fs.create_dir("tmp/mine")?;
+    fs.create_dir("tmp/mine/locked")?;
+    fs.create_file_with_content("tmp/mine/locked/f1", "Hello")?;
+    fs.change_permissions("tmp/mine/locked", "555")?;
+    fs.change_owner("tmp/mine", Some(1000), Some(1000))?;
+    fs.change_owner("tmp/mine/locked", Some(1000), Some(1000))?;
+    fs.create_file_with_content("tmp/private/secret", "Hello")?;
+    fs.create_dir("tmp/empty")?;
+    fs.change_permissions("tmp/empty", "700")?;
+    let free = fs.volume_usage()?.free_bytes();
+
+    // every directory of the tree has to be emptied, not just its parent changed
+    fs.set_credentials(Credentials::new(1000, 1000));
+    let err = fs.delete_dir("/tmp/private").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToRead(_))
+    ));
+    let err = fs.delete_dir("/tmp/mine").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::NoPermissionToWrite(_))
+    ));
+    fs.set_credentials(Credentials::root());
+    assert_eq!(fs.read("tmp/private/secret")?, b"Hello");
+    assert_eq!(fs.read("tmp/mine/locked/f1")?, b"Hello");
+    assert_eq!(fs.volume_usage()?.free_bytes(), free);
+
+    // an empty directory only needs write access to its parent
+    fs.set_credentials(Credentials::new(1000, 1000));
+    fs.delete_dir("/tmp/empty")?;
+    fs.set_credentials(Credentials::root());
+    fs.change_permissions("tmp/mine/locked", "755")?;
+    fs.set_credentials(Credentials::new(1000, 1000));
+    fs.delete_dir("/tmp/mine")?;
+    fs.set_credentials(Credentials::root());
+    assert_eq!(fs.read_dir("tmp")?.len(), 1);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
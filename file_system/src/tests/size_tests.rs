@@ -0,0 +1,97 @@
+use crate::prelude::*;
+use crate::FileSystem;
+
+fn entry_size(fs: &FileSystem, parent: &str, name: &str) -> u64 {
+    fs.traverse_dir(parent.to_string())
+        .unwrap()
+        .get_entry(&name.into())
+        .unwrap()
+        .size
+}
+
+#[test]
+fn test_sizes_follow_create_and_delete() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d1/d2")?;
+    fs.create_file_with_content("d1/d2/f1", "Hello, World!")?;
+    let f1_size = entry_size(&fs, "/d1/d2", "f1");
+    assert_eq!(entry_size(&fs, "/d1", "d2"), f1_size);
+    assert_eq!(entry_size(&fs, "/", "d1"), f1_size);
+
+    fs.delete_file("d1/d2/f1")?;
+    assert_eq!(entry_size(&fs, "/d1", "d2"), 0);
+    assert_eq!(entry_size(&fs, "/", "d1"), 0);
+
+    fs.create_file_with_content("d1/d2/f1", "Hello, World!")?;
+    fs.delete_dir("d1/d2")?;
+    assert_eq!(entry_size(&fs, "/", "d1"), 0);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_sizes_follow_move_and_append() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d2")?;
+    fs.create_file_with_content("d1/f1", "Hello, World!")?;
+    fs.create_file_with_content("d1/f2", "Hello!")?;
+    let f1_size = entry_size(&fs, "/d1", "f1");
+    let f2_size = entry_size(&fs, "/d1", "f2");
+
+    fs.move_entry("d1/f1", "d2")?;
+    assert_eq!(entry_size(&fs, "/", "d1"), f2_size);
+    assert_eq!(entry_size(&fs, "/", "d2"), f1_size);
+
+    fs.append_file("d2/f1", "d1/f2")?;
+    let appended = entry_size(&fs, "/d1", "f2");
+    assert!(appended > f2_size);
+    assert_eq!(entry_size(&fs, "/", "d1"), appended);
+
+    fs.change_dir("d1")?;
+    fs.copy_entry("f2", "/d2/f3")?;
+    assert_eq!(entry_size(&fs, "/", "d2"), f1_size + appended);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_recompute_dir_sizes() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d1/d2")?;
+    fs.create_file_with_content("d1/d2/f1", "Hello, World!")?;
+    fs.create_file_with_content("d1/f2", "Hello!")?;
+    let expected = entry_size(&fs, "/", "d1");
+
+    // corrupt the stored size and rebuild it
+    let mut root = fs.traverse_dir("/".to_string())?;
+    root.get_entry_mut(&"d1".into()).unwrap().size = 12345;
+    fs.write_dir_block(&root)?;
+
+    let total = fs.recompute_dir_sizes()?;
+    assert_eq!(total, expected);
+    assert_eq!(entry_size(&fs, "/", "d1"), expected);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_sizes_count_every_name() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/f1", "Hello, World!")?;
+    fs.create_hard_link("d1/f1", "d1/link")?;
+
+    // the size adds up the names, the usage the data
+    assert_eq!(entry_size(&fs, "/", "d1"), 26);
+    assert_eq!(fs.recompute_dir_sizes()?, 26);
+    assert_eq!(fs.dir_usage("d1")?.bytes, 13);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
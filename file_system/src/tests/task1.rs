@@ -1,5 +1,3 @@
-use rustic_disk::Disk;
-
 use crate::prelude::*;
 use crate::FileSystem;
 
@@ -49,7 +47,9 @@ fn test_long_name() -> anyhow::Result<()> {
         "AbcdefghijAbcddefghijAbcdefghijAbcdefghijAbcdefghijAbcde",
         "Hello, World!",
     );
-    assert!(t41.is_err());
+    assert!(t41.is_ok());
+    let t42 = fs.create_file_with_content("Abcdefghij".repeat(26).as_str(), "Hello, World!");
+    assert!(t42.is_err());
     fs.disk.delete_disk()?;
     Ok(())
 }
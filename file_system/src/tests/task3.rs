@@ -1,3 +1,6 @@
+use crate::prelude::*;
+use crate::FileSystem;
+
 #[test]
 fn test_copy() -> anyhow::Result<()> {
     let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
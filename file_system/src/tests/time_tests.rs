@@ -0,0 +1,90 @@
+use crate::dir_entry::DirEntry;
+use crate::prelude::*;
+use crate::FileSystem;
+
+/// Sets all times of the entry at `path` to `time`, so that updates can be told apart.
+fn backdate(fs: &mut FileSystem, path: &str, time: i64) -> anyhow::Result<()> {
+    let (parent, name) = crate::utils::path_handler::split_path(path.to_string());
+    let mut block = fs.traverse_dir(parent)?;
+    let entry = block.get_entry_mut(&name.into()).unwrap();
+    entry.created = time;
+    entry.modified = time;
+    entry.accessed = time;
+    fs.write_dir_block(&block)?;
+    fs.update_curr_dir()
+}
+
+fn entry(fs: &FileSystem, path: &str) -> anyhow::Result<DirEntry> {
+    fs.lookup_entry(path, false)
+}
+
+#[test]
+fn test_times_are_updated() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/f1", "Hello")?;
+    fs.create_file_with_content("f2", "World")?;
+
+    let f1 = entry(&fs, "/d1/f1")?;
+    assert!(f1.created > 0);
+    assert_eq!(f1.created, f1.modified);
+    assert!(fs.stat("d1/f1").is_ok());
+
+    // appending modifies the destination and reads the source
+    backdate(&mut fs, "/d1/f1", 1)?;
+    backdate(&mut fs, "/f2", 1)?;
+    fs.append_file("f2", "d1/f1")?;
+    let f1 = entry(&fs, "/d1/f1")?;
+    assert_eq!(f1.created, 1);
+    assert!(f1.modified > 1);
+    assert!(entry(&fs, "/f2")?.accessed > 1);
+
+    // creating and removing entries modifies their directory
+    backdate(&mut fs, "/d1", 1)?;
+    fs.create_file_with_content("d1/f3", "!")?;
+    assert!(entry(&fs, "/d1")?.modified > 1);
+    backdate(&mut fs, "/d1", 1)?;
+    fs.remove_entry("d1/f3")?;
+    assert!(entry(&fs, "/d1")?.modified > 1);
+
+    // a copy is a new file, a move keeps the times of the entry
+    fs.copy_entry("f2", "d1")?;
+    assert!(entry(&fs, "/d1/f2")?.created > 1);
+    backdate(&mut fs, "/f2", 1)?;
+    fs.move_entry("f2", "f4")?;
+    assert_eq!(entry(&fs, "/f4")?.modified, 1);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_atime_policy() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_file_with_content("f1", "Hello")?;
+    fs.create_hard_link("f1", "f2")?;
+    assert_eq!(fs.atime_policy(), AtimePolicy::Relatime);
+    assert!("sometimes".parse::<AtimePolicy>().is_err());
+
+    fs.set_atime_policy("noatime".parse()?);
+    backdate(&mut fs, "/f1", 1)?;
+    fs.read_file("f1")?;
+    assert_eq!(entry(&fs, "/f1")?.accessed, 1);
+
+    // an access time older than a day is refreshed, on every name of the file
+    fs.set_atime_policy(AtimePolicy::Relatime);
+    fs.read_file("f1")?;
+    let accessed = entry(&fs, "/f1")?.accessed;
+    assert!(accessed > 1);
+    assert_eq!(entry(&fs, "/f2")?.accessed, accessed);
+
+    fs.set_atime_policy(AtimePolicy::Strict);
+    backdate(&mut fs, "/f1", 1)?;
+    fs.read_file("f1")?;
+    assert!(entry(&fs, "/f1")?.accessed > 1);
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
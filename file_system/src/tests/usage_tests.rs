@@ -0,0 +1,65 @@
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_df_counts_allocated_blocks() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    let before = fs.volume_usage()?;
+    assert_eq!(before.used_blocks, 2); // root directory and FAT
+    assert_eq!(before.used_blocks + before.free_blocks, before.total_blocks);
+
+    fs.create_file_with_content("f1", "Hello, World!")?;
+    fs.create_dir("d1")?;
+    let after = fs.volume_usage()?;
+    assert_eq!(after.used_blocks, before.used_blocks + 2);
+    assert_eq!(after.free_bytes(), after.free_blocks * after.block_size);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_du_is_recursive() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_dir("d1/d2")?;
+    fs.create_file_with_content("d1/f1", "Hello, World!")?;
+    fs.create_file_with_content("d1/d2/f2", "Hello, World!".repeat(500).as_str())?;
+
+    let usage = fs.dir_usage("d1")?;
+    assert_eq!(usage.path, "/d1");
+    assert_eq!(usage.children.len(), 1);
+    assert_eq!(usage.children[0].path, "/d1/d2");
+    // d1 + f1 + d2 + two blocks for f2
+    assert_eq!(usage.blocks, 5);
+    assert_eq!(usage.bytes, usage.children[0].bytes + fs.dir_usage("d1/f1")?.bytes);
+
+    let root = fs.dir_usage("/")?;
+    assert_eq!(root.blocks, usage.blocks + 1);
+    assert!(fs.du("/", false).is_ok());
+    assert!(fs.du("/", true).is_ok());
+    assert!(fs.dir_usage("missing").is_err());
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_du_counts_hard_links_once() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("d1")?;
+    fs.create_file_with_content("d1/f1", "Hello, World!".repeat(500).as_str())?;
+    fs.create_hard_link("d1/f1", "d1/link")?;
+    fs.create_hard_link("d1/f1", "link")?;
+
+    // the FAT block is the only one outside the tree
+    let root = fs.dir_usage("/")?;
+    assert_eq!(root.blocks + 1, fs.volume_usage()?.used_blocks);
+    assert_eq!(root.bytes, 6500);
+    let usage = fs.dir_usage("d1")?;
+    assert_eq!((usage.blocks, usage.bytes), (3, 6500));
+    assert_eq!(fs.dir_usage("link")?.blocks, 2);
+    fs.disk.delete_disk()?;
+    Ok(())
+}
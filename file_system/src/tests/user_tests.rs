@@ -0,0 +1,75 @@
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_user_database() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    // a fresh volume only knows root, locked until it gets a password
+    assert_eq!(fs.users()?.len(), 1);
+    assert!(fs.login("root", "").is_err());
+    assert!(fs.login("root", "anything").is_err());
+    assert!(fs.is_locked("root")?);
+    fs.set_password("root", "toor")?;
+    assert!(!fs.is_locked("root")?);
+    fs.login("root", "toor")?;
+
+    let alice = fs.add_user("alice")?;
+    assert_eq!((alice.uid, alice.gid), (FIRST_USER_ID, FIRST_USER_ID));
+    assert_eq!(alice.home, "/home/alice");
+    assert_eq!(fs.add_user("bob")?.uid, FIRST_USER_ID + 1);
+    assert!(fs.add_user("alice").is_err());
+    assert!(fs.add_user("Not Valid").is_err());
+
+    let home = fs.lookup_entry("/home/alice", false)?;
+    assert_eq!((home.uid, home.gid), (alice.uid, alice.gid));
+    let shadow = fs.lookup_entry(SHADOW_PATH, false)?;
+    assert_eq!(shadow.mode, 0o600);
+
+    // new accounts are locked until they get a password, which is stored hashed
+    assert!(fs.authenticate("alice", "").is_err());
+    fs.set_password("alice", "secret")?;
+    let shadow = String::from_utf8(fs.read_content(&fs.lookup_entry(SHADOW_PATH, false)?)?)?;
+    assert!(shadow.contains("alice:$argon2"));
+    assert!(!shadow.contains("secret"));
+    assert!(fs.authenticate("alice", "wrong").is_err());
+
+    let user = fs.login("alice", "secret")?;
+    assert_eq!(fs.credentials(), user.credentials());
+    assert_eq!(fs.curr_block.path, "/home/alice");
+    fs.create_file_with_content("notes", "Hello")?;
+    assert_eq!(fs.lookup_entry("/home/alice/notes", false)?.uid, alice.uid);
+    // other users can't read the shadow file or manage accounts
+    assert!(fs.read_file(SHADOW_PATH).is_err());
+    assert!(fs.add_user("carol").is_err());
+    assert!(fs.set_password("bob", "secret").is_err());
+    assert!(fs.format().is_err());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_switch_user() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.add_user("alice")?;
+    fs.add_user("bob")?;
+    fs.set_password("bob", "hunter2")?;
+
+    // root switches without a password, everyone else needs the one of the target
+    let alice = fs.switch_user("alice", None)?;
+    assert_eq!(fs.current_user()?, Some(alice));
+    assert!(fs.switch_user("bob", None).is_err());
+    assert!(fs.switch_user("bob", Some("wrong")).is_err());
+    fs.switch_user("bob", Some("hunter2"))?;
+    assert_eq!(fs.current_user()?.unwrap().name, "bob");
+    fs.set_password("bob", "hunter3")?;
+    assert!(fs.authenticate("bob", "hunter3").is_ok());
+    assert!(fs.switch_user("root", Some("")).is_err());
+    assert!(fs.switch_user("root", Some("anything")).is_err());
+    assert!(!fs.credentials().is_root());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
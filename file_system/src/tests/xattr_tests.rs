@@ -0,0 +1,74 @@
+use crate::errors::FileError;
+use crate::prelude::*;
+use crate::FileSystem;
+
+#[test]
+fn test_xattrs() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("docs")?;
+    fs.create_file_with_content("docs/report", "Hello")?;
+
+    fs.set_xattr("docs/report", "user.content-type", b"text/plain")?;
+    fs.set_xattr("docs/report", "user.author", b"alice")?;
+    fs.set_xattr("docs", "user.tags", b"work,draft")?;
+    fs.set_xattr("docs/report", "user.author", b"bob")?;
+    assert_eq!(fs.get_xattr("docs/report", "user.author")?, b"bob");
+    assert_eq!(
+        fs.list_xattr("docs/report")?,
+        ["user.author", "user.content-type"]
+    );
+    assert_eq!(fs.get_xattr("docs", "user.tags")?, b"work,draft");
+
+    fs.remove_xattr("docs/report", "user.author")?;
+    let err = fs.get_xattr("docs/report", "user.author").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FileError::XattrNotFound(_))
+    ));
+    assert!(fs.remove_xattr("docs/report", "user.author").is_err());
+    assert!(fs.set_xattr("docs/report", "", b"x").is_err());
+    assert!(fs.set_xattr("docs/report", &"a".repeat(256), b"x").is_err());
+    assert!(fs.set_xattr("/", "user.tags", b"x").is_err());
+
+    // values larger than a block are chained through the FAT
+    let big = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs.set_xattr("docs/report", "user.thumbnail", &big)?;
+    assert_eq!(fs.get_xattr("docs/report", "user.thumbnail")?, big);
+
+    // reading needs read and changing needs write permission on the entry
+    fs.set_credentials(Credentials::new(1000, 1000));
+    assert!(fs.list_xattr("docs/report").is_ok());
+    assert!(fs.set_xattr("docs/report", "user.author", b"eve").is_err());
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
+
+#[test]
+fn test_xattrs_copy_and_move() -> anyhow::Result<()> {
+    let mut fs = FileSystem::new(Box::new(StdIOHandler))?;
+    fs.format()?;
+    fs.create_dir("dest")?;
+    fs.create_file_with_content("f1", "Hello")?;
+    fs.set_xattr("f1", "user.author", b"alice")?;
+
+    fs.copy_entry("f1", "f2")?;
+    fs.move_entry("f1", "dest/f1")?;
+    assert_eq!(fs.get_xattr("dest/f1", "user.author")?, b"alice");
+    assert_eq!(fs.get_xattr("f2", "user.author")?, b"alice");
+
+    // the copy has attributes of its own
+    fs.set_xattr("f2", "user.author", b"bob")?;
+    assert_eq!(fs.get_xattr("dest/f1", "user.author")?, b"alice");
+    fs.delete_file("f2")?;
+    assert_eq!(fs.get_xattr("dest/f1", "user.author")?, b"alice");
+
+    // hard links share them
+    fs.create_hard_link("dest/f1", "f3")?;
+    fs.set_xattr("f3", "user.tags", b"linked")?;
+    assert_eq!(fs.get_xattr("dest/f1", "user.tags")?, b"linked");
+
+    fs.disk.delete_disk()?;
+    Ok(())
+}
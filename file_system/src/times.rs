@@ -0,0 +1,175 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{Local, TimeZone, Utc};
+use logger_macro::trace_log;
+use prettytable::{format, row, Table};
+
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::{FSError, FileError};
+use crate::info::EntryInfo;
+use crate::traits::Metadata;
+use crate::utils::path_handler::{anchor, split_path};
+use crate::FileSystem;
+
+/// Seconds after which `AtimePolicy::Relatime` updates the access time even if the file wasn't modified.
+const RELATIME_INTERVAL: i64 = 24 * 60 * 60;
+
+/// When reading a file updates its access time.
+///
+/// Every update rewrites the directory holding the file,
+/// so the policy trades accurate access times for fewer writes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AtimePolicy {
+    /// Every read updates the access time.
+    Strict,
+    /// Reads update the access time if it is older than the modification time or a day old.
+    #[default]
+    Relatime,
+    /// Reads never update the access time.
+    NoAtime,
+}
+
+impl AtimePolicy {
+    fn should_update(&self, entry: &DirEntry, now: i64) -> bool {
+        match self {
+            AtimePolicy::Strict => true,
+            AtimePolicy::Relatime => {
+                entry.accessed <= entry.modified || now - entry.accessed >= RELATIME_INTERVAL
+            }
+            AtimePolicy::NoAtime => false,
+        }
+    }
+}
+
+impl fmt::Display for AtimePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtimePolicy::Strict => write!(f, "strict"),
+            AtimePolicy::Relatime => write!(f, "relatime"),
+            AtimePolicy::NoAtime => write!(f, "noatime"),
+        }
+    }
+}
+
+impl FromStr for AtimePolicy {
+    type Err = FSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(AtimePolicy::Strict),
+            "relatime" => Ok(AtimePolicy::Relatime),
+            "noatime" => Ok(AtimePolicy::NoAtime),
+            _ => Err(FSError::InvalidAtimePolicy(s.to_string())),
+        }
+    }
+}
+
+/// The current time as seconds since the Unix epoch.
+pub(crate) fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+/// Formats a timestamp in local time, `0` is shown as `-` since it marks an unknown time.
+pub(crate) fn format_time(timestamp: i64) -> String {
+    match Local.timestamp_opt(timestamp, 0).single() {
+        Some(time) if timestamp != 0 => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+impl FileSystem {
+    pub fn atime_policy(&self) -> AtimePolicy {
+        self.atime_policy
+    }
+
+    pub fn set_atime_policy(&mut self, policy: AtimePolicy) {
+        self.atime_policy = policy;
+    }
+
+    /// Returns the entry at the absolute `path`, the root has an entry without a parent.
+    #[trace_log]
+    pub(crate) fn lookup_entry(&self, path: &str, follow_last: bool) -> Result<DirEntry> {
+        let abs_path = self.resolve_path(path, follow_last)?;
+        let (parent, name) = split_path(abs_path);
+        if name.is_empty() {
+            return Ok(self.traverse_dir(parent)?.parent_entry);
+        }
+
+        self.traverse_bucket(parent, &name)?
+            .get_entry(&name.into())
+            .cloned()
+            .ok_or(FileError::FileNotFound.into())
+    }
+
+    /// Marks the entry at `path` as modified now, the root has no stored times and is skipped.
+    #[trace_log]
+    pub(crate) fn touch_modified(&mut self, path: &str) -> Result<()> {
+        if self.resolve_path(&anchor(path), false)? == "/" {
+            return Ok(());
+        }
+        let now = now();
+        self.update_entry_at(path, |entry| entry.modified = now)
+    }
+
+    /// Marks the entry at `path` as accessed now if the access time policy allows it,
+    /// the root has no stored times and is skipped.
+    #[trace_log]
+    pub(crate) fn touch_accessed(&mut self, path: &str) -> Result<()> {
+        if self.resolve_path(&anchor(path), false)? == "/" {
+            return Ok(());
+        }
+        let now = now();
+        let entry = self.lookup_entry(&anchor(path), false)?;
+        if !self.atime_policy.should_update(&entry, now) {
+            return Ok(());
+        }
+
+        self.update_entry_at(path, |entry| entry.accessed = now)
+    }
+}
+
+impl Metadata for FileSystem {
+    /// Prints the metadata of the entry at `path`, symbolic links are not followed.
+    #[trace_log]
+    fn stat(&mut self, path: &str) -> Result<()> {
+        let entry = self.metadata(path)?;
+        let entry_type = match entry.file_type {
+            FileType::File => "File",
+            FileType::Directory => "Directory",
+            FileType::Symlink => "Symlink",
+        };
+
+        let mut table = Table::new();
+        table.add_row(row!["Name", entry.name]);
+        table.add_row(row!["Type", entry_type]);
+        if let Some(target) = &entry.target {
+            table.add_row(row!["Target", target]);
+        }
+        table.add_row(row!["Size (Bytes)", entry.size.to_string()]);
+        table.add_row(row!["Blocks", entry.blocks.to_string()]);
+        table.add_row(row!["Links", entry.link_count.to_string()]);
+        table.add_row(row!["Block Number", entry.blk_num.to_string()]);
+        table.add_row(row![
+            "Access Rights",
+            format!("{} ({:03o})", entry.permissions(), entry.mode)
+        ]);
+        table.add_row(row!["Owner", entry.uid.to_string()]);
+        table.add_row(row!["Group", entry.gid.to_string()]);
+        table.add_row(row!["Created", format_time(entry.created)]);
+        table.add_row(row!["Modified", format_time(entry.modified)]);
+        table.add_row(row!["Accessed", format_time(entry.accessed)]);
+        table.set_format(*format::consts::FORMAT_CLEAN);
+
+        self.io_handler.write(table.to_string())?;
+        Ok(())
+    }
+
+    /// Returns the metadata of the entry at `path`, symbolic links are not followed.
+    #[trace_log]
+    fn metadata(&self, path: &str) -> Result<EntryInfo> {
+        let entry = self.lookup_entry(path, false)?;
+        self.entry_info(&entry)
+    }
+}
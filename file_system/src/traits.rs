@@ -1,8 +1,17 @@
 use anyhow::Result;
 use std::fmt::Debug;
+use std::io::Read;
+
+use crate::acl::Acl;
+use crate::format::FormatOptions;
+use crate::info::EntryInfo;
+use crate::usage::{DirUsage, VolumeUsage};
+use crate::users::User;
+use crate::walk::{ReadDir, Walk};
 
 pub trait Format {
     fn format(&mut self) -> Result<()>;
+    fn format_with(&mut self, options: FormatOptions) -> Result<()>;
 }
 
 pub trait InputConstructor {
@@ -10,7 +19,8 @@ pub trait InputConstructor {
 }
 
 pub trait Input {
-    fn read_lines(&mut self) -> Result<String>;
+    /// Reads everything the source holds as raw bytes.
+    fn read_data(&mut self) -> Result<Vec<u8>>;
 }
 
 pub trait File {
@@ -19,25 +29,87 @@ pub trait File {
         T: Input + Debug;
     // this is only since we need to test the file system, and we need to create a file with content
     fn create_file_with_content(&mut self, name: &str, content: &str) -> Result<()>;
+    fn create_file_with_bytes(&mut self, name: &str, content: &[u8]) -> Result<()>;
+    fn create_file_from_base64(&mut self, name: &str, encoded: &str) -> Result<()>;
     fn create_file_stdio(&mut self, name: &str) -> Result<()>;
+    fn create_file_from_reader<R: Read>(&mut self, path: &str, reader: R) -> Result<()>;
+    fn write_file_from_reader<R: Read>(&mut self, path: &str, reader: R) -> Result<()>;
     fn delete_file(&mut self, path: &str) -> Result<()>;
     fn read_file(&mut self, name: &str) -> Result<()>;
+    fn read(&mut self, path: &str) -> Result<Vec<u8>>;
     fn append_file(&mut self, source: &str, dest: &str) -> Result<()>;
+    fn append_bytes(&mut self, path: &str, data: &[u8]) -> Result<()>;
+    fn write_at(&mut self, path: &str, offset: u64, data: &[u8]) -> Result<()>;
+    fn append_input<T>(&mut self, path: &str, input_source: &mut T) -> Result<()>
+    where
+        T: Input + Debug;
+    fn read_range(&mut self, path: &str, offset: u64, len: u64) -> Result<Vec<u8>>;
+    fn truncate(&mut self, path: &str, len: u64) -> Result<()>;
+    fn hexdump(&mut self, path: &str) -> Result<()>;
+    fn xxd(&mut self, path: &str) -> Result<()>;
 }
 
 pub trait Directory {
     fn create_dir(&mut self, name: &str) -> Result<()>;
     fn delete_dir(&mut self, path: &str) -> Result<()>;
     fn list_dir(&mut self) -> Result<()>;
+    fn read_dir(&self, path: &str) -> Result<ReadDir<'_>>;
+    fn walk(&self, path: &str) -> Walk<'_>;
 }
 
 pub trait DirEntryHandling {
     fn move_entry(&mut self, source: &str, dest: &str) -> Result<()>;
+    fn move_entry_overwrite(&mut self, source: &str, dest: &str) -> Result<()>;
     fn copy_entry(&mut self, source: &str, dest: &str) -> Result<()>;
 }
 
-pub trait Permissions {
+pub trait Links {
+    fn create_symlink(&mut self, target: &str, path: &str) -> Result<()>;
+    fn create_hard_link(&mut self, target: &str, path: &str) -> Result<()>;
+    fn read_link(&self, path: &str) -> Result<String>;
+    fn readlink(&mut self, path: &str) -> Result<()>;
+}
+
+pub trait Metadata {
+    fn stat(&mut self, path: &str) -> Result<()>;
+    fn metadata(&self, path: &str) -> Result<EntryInfo>;
+}
+
+pub trait Users {
+    fn users(&self) -> Result<Vec<User>>;
+    fn add_user(&mut self, name: &str) -> Result<User>;
+    fn set_password(&mut self, name: &str, password: &str) -> Result<()>;
+    fn authenticate(&self, name: &str, password: &str) -> Result<User>;
+    fn switch_user(&mut self, name: &str, password: Option<&str>) -> Result<User>;
+    fn login(&mut self, name: &str, password: &str) -> Result<User>;
+}
+
+pub trait Usage {
+    fn volume_usage(&self) -> Result<VolumeUsage>;
+    fn dir_usage(&self, path: &str) -> Result<DirUsage>;
+    fn df(&mut self) -> Result<()>;
+    fn du(&mut self, path: &str, summarize: bool) -> Result<()>;
+}
+
+pub trait PermissionHandling {
     fn change_permissions(&mut self, path: &str, permissions: &str) -> Result<()>;
+    fn change_permissions_recursive(&mut self, path: &str, permissions: &str) -> Result<()>;
+    fn change_owner(&mut self, path: &str, uid: Option<u16>, gid: Option<u16>) -> Result<()>;
+}
+
+pub trait AclHandling {
+    fn get_acl(&self, path: &str) -> Result<Acl>;
+    fn set_acl(&mut self, path: &str, acl: &Acl) -> Result<()>;
+    fn modify_acl(&mut self, path: &str, specs: &str) -> Result<()>;
+    fn remove_acl_entries(&mut self, path: &str, specs: &str) -> Result<()>;
+    fn getfacl(&mut self, path: &str) -> Result<()>;
+}
+
+pub trait XattrHandling {
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>>;
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()>;
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>>;
+    fn remove_xattr(&mut self, path: &str, name: &str) -> Result<()>;
 }
 
 pub trait IOHandlerClone {
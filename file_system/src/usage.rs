@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use logger_macro::trace_log;
+use prettytable::{format, row, Row, Table};
+
+use rustic_disk::Disk;
+
+use crate::dir_entry::{DirEntry, FileType};
+use crate::errors::{FSError, FileError};
+use crate::fat::FatType;
+use crate::traits::Usage;
+use crate::utils::path_handler::{absolutize_from, split_path};
+use crate::FileSystem;
+
+/// Space usage of the whole volume, derived from the FAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolumeUsage {
+    pub block_size: u64,
+    pub total_blocks: u64,
+    pub used_blocks: u64,
+    pub free_blocks: u64,
+}
+
+impl VolumeUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.total_blocks * self.block_size
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used_blocks * self.block_size
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.free_blocks * self.block_size
+    }
+}
+
+/// Recursive space usage of a file or directory.
+///
+/// `blocks` counts every block allocated to the entry, including the blocks
+/// holding directory listings, while `bytes` is the sum of the file sizes.
+/// Holes of sparse files count towards `bytes` but take no blocks.
+/// A file with several names in the tree is only counted for the first one.
+/// `children` holds the usage of each subdirectory, in listing order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirUsage {
+    pub path: String,
+    pub blocks: u64,
+    pub bytes: u64,
+    pub children: Vec<DirUsage>,
+}
+
+impl DirUsage {
+    /// The space the allocated blocks take on the volume.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.blocks * Disk::BLOCK_SIZE as u64
+    }
+}
+
+impl FileSystem {
+    /// Counts the number of blocks in the FAT chain starting at `start_blk`.
+    ///
+    /// # Errors
+    /// Returns `FSError::InvalidBlockReference`
+    /// if a block reference in the FAT is neither `Taken` nor `EOF`.
+    #[trace_log]
+    pub fn count_chain_blocks(&self, start_blk: u16) -> Result<u64> {
+        let mut blk_num = start_blk;
+        let mut count = 0;
+
+        loop {
+            match self.fat.get(blk_num as usize) {
+                Some(&FatType::Taken(next_blk)) => {
+                    count += 1;
+                    blk_num = next_blk;
+                }
+                Some(&FatType::EOF) => {
+                    count += 1;
+                    break;
+                }
+                _ => return Err(FSError::InvalidBlockReference.into()),
+            }
+
+            if count > self.fat.len() as u64 {
+                return Err(FSError::InvalidBlockReference.into());
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Computes the usage of `entry` and everything below it,
+    /// `seen` holds the data chains of files with several names that were already counted.
+    #[trace_log]
+    fn entry_usage(
+        &self,
+        entry: &DirEntry,
+        path: String,
+        seen: &mut HashSet<u16>,
+    ) -> Result<DirUsage> {
+        if entry.file_type != FileType::Directory {
+            let already_counted = entry.link_count > 1 && !seen.insert(entry.blk_num);
+            let (blocks, bytes) = if already_counted {
+                (0, 0)
+            } else {
+                (self.count_chain_blocks(entry.blk_num)?, entry.size)
+            };
+            return Ok(DirUsage {
+                path,
+                blocks,
+                bytes,
+                children: Vec::new(),
+            });
+        }
+
+        let blocks = self.count_chain_blocks(entry.blk_num)?;
+
+        let block = self.read_dir_block(entry)?;
+        let mut usage = DirUsage {
+            path: path.clone(),
+            blocks,
+            bytes: 0,
+            children: Vec::new(),
+        };
+
+        for child in block.entries.iter().filter(|entry| !entry.name.is_empty()) {
+            let child_path = format!("{}/{}", path.trim_end_matches('/'), child.name);
+            let child_usage = self.entry_usage(child, child_path, seen)?;
+            usage.blocks += child_usage.blocks;
+            usage.bytes += child_usage.bytes;
+            if child.file_type == FileType::Directory {
+                usage.children.push(child_usage);
+            }
+        }
+
+        Ok(usage)
+    }
+}
+
+fn add_du_rows(table: &mut Table, usage: &DirUsage) {
+    for child in &usage.children {
+        add_du_rows(table, child);
+    }
+    table.add_row(du_row(usage));
+}
+
+fn du_row(usage: &DirUsage) -> Row {
+    row![
+        usage.blocks.to_string(),
+        usage.allocated_bytes().to_string(),
+        usage.bytes.to_string(),
+        usage.path.clone(),
+    ]
+}
+
+impl Usage for FileSystem {
+    /// Computes the total, used and free space of the volume from the FAT.
+    #[trace_log]
+    fn volume_usage(&self) -> Result<VolumeUsage> {
+        let total_blocks = self.fat.len() as u64;
+        let free_blocks = self
+            .fat
+            .iter()
+            .filter(|&blk| *blk == FatType::Free)
+            .count() as u64;
+
+        Ok(VolumeUsage {
+            block_size: Disk::BLOCK_SIZE as u64,
+            total_blocks,
+            used_blocks: total_blocks - free_blocks,
+            free_blocks,
+        })
+    }
+
+    /// Computes the recursive usage of the file or directory at `path`.
+    #[trace_log]
+    fn dir_usage(&self, path: &str) -> Result<DirUsage> {
+        let abs_path = absolutize_from(path, &self.curr_block.path);
+        let abs_path = if abs_path.starts_with('/') {
+            abs_path
+        } else {
+            format!("/{}", abs_path)
+        };
+
+        if abs_path == "/" {
+            let root = self.traverse_dir(abs_path.clone())?;
+            return self.entry_usage(&root.parent_entry, abs_path, &mut HashSet::new());
+        }
+
+        let (parent, name) = split_path(abs_path.clone());
+        let parent_block = self.traverse_bucket(parent, &name)?;
+        let entry = parent_block
+            .get_entry(&name.into())
+            .ok_or(FileError::FileNotFound)?;
+
+        self.entry_usage(entry, abs_path, &mut HashSet::new())
+    }
+
+    /// Prints the volume usage as a table.
+    #[trace_log]
+    fn df(&mut self) -> Result<()> {
+        let usage = self.volume_usage()?;
+
+        let mut table = Table::new();
+        table.set_titles(row!["", "Blocks", "Bytes"]);
+        table.add_row(row![
+            "Total",
+            usage.total_blocks.to_string(),
+            usage.total_bytes().to_string()
+        ]);
+        table.add_row(row![
+            "Used",
+            usage.used_blocks.to_string(),
+            usage.used_bytes().to_string()
+        ]);
+        table.add_row(row![
+            "Free",
+            usage.free_blocks.to_string(),
+            usage.free_bytes().to_string()
+        ]);
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+
+        self.io_handler.write(table.to_string())?;
+        Ok(())
+    }
+
+    /// Prints the recursive usage of `path`, one row per directory unless `summarize` is set.
+    ///
+    /// The allocated size only counts blocks, the logical size counts the holes of sparse files too.
+    #[trace_log]
+    fn du(&mut self, path: &str, summarize: bool) -> Result<()> {
+        let usage = self.dir_usage(path)?;
+
+        let mut table = Table::new();
+        table.set_titles(row!["Blocks", "Allocated (Bytes)", "Size (Bytes)", "Path"]);
+        if summarize {
+            table.add_row(du_row(&usage));
+        } else {
+            add_du_rows(&mut table, &usage);
+        }
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+
+        self.io_handler.write(table.to_string())?;
+        Ok(())
+    }
+}
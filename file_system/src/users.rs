@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use logger_macro::trace_log;
+
+use crate::credentials::{Credentials, ROOT_GID, ROOT_UID};
+use crate::errors::{FileError, UserError};
+use crate::traits::{Directory, File, PermissionHandling, Users};
+use crate::FileSystem;
+
+/// One line per user: `name:x:uid:gid:home`, readable by everyone.
+pub const PASSWD_PATH: &str = "/etc/passwd";
+/// One line per user: `name:hash`, readable by the superuser only.
+pub const SHADOW_PATH: &str = "/etc/shadow";
+const ETC_PATH: &str = "/etc";
+const HOME_PATH: &str = "/home";
+
+/// The first id handed out by `add_user`, lower ids are reserved.
+pub const FIRST_USER_ID: u16 = 1000;
+const MAX_USERNAME_LEN: usize = 32;
+
+/// Shadow field of an account that can't log in until it gets a password.
+const LOCKED: &str = "!";
+
+/// An account from the user database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    pub uid: u16,
+    pub gid: u16,
+    pub home: String,
+}
+
+impl User {
+    fn root() -> Self {
+        User {
+            name: "root".to_string(),
+            uid: ROOT_UID,
+            gid: ROOT_GID,
+            home: "/".to_string(),
+        }
+    }
+
+    pub fn credentials(&self) -> Credentials {
+        Credentials::new(self.uid, self.gid)
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}:x:{}:{}:{}", self.name, self.uid, self.gid, self.home)
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split(':');
+        let name = fields.next()?.to_string();
+        let uid = fields.nth(1)?.parse().ok()?;
+        let gid = fields.next()?.parse().ok()?;
+        let home = fields.next()?.to_string();
+        Some(User {
+            name,
+            uid,
+            gid,
+            home,
+        })
+    }
+}
+
+/// Hashes `password` with Argon2 and a random salt into a PHC string.
+fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| UserError::PasswordHash(e.to_string()))?;
+    Ok(hash.to_string())
+}
+
+/// Checks `password` against a shadow field,
+/// an empty or locked field never matches.
+fn verify_password(stored: &str, password: &str) -> bool {
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Checks that `name` can be used for a new account.
+fn check_username(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_USERNAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(UserError::InvalidUsername(name.to_string()).into())
+    }
+}
+
+impl FileSystem {
+    /// Reads a file of the user database, `None` if it doesn't exist.
+    #[trace_log]
+    fn read_system_file(&self, path: &str) -> Result<Option<String>> {
+        let entry = match self.lookup_entry(path, false) {
+            Ok(entry) => entry,
+            Err(e) if matches!(e.downcast_ref(), Some(FileError::FileNotFound)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(String::from_utf8(self.read_content(&entry)?)?))
+    }
+
+    /// Replaces a file of the user database with `content` and gives it `mode`.
+    #[trace_log]
+    fn write_system_file(&mut self, path: &str, content: &str, mode: &str) -> Result<()> {
+        self.as_root(|fs| {
+            if fs.lookup_entry(path, false).is_ok() {
+                fs.delete_file(path)?;
+            }
+            fs.create_file_with_content(path, content)?;
+            fs.change_permissions(path, mode)
+        })
+    }
+
+    /// Creates `/etc/passwd`, `/etc/shadow` and `/home` if they are missing,
+    /// the database starts with a `root` account that is locked until it gets a password.
+    ///
+    /// A freshly formatted volume has no database and behaves as if it only held the locked `root`,
+    /// the files are written the first time an account is added or changed.
+    #[trace_log]
+    fn ensure_user_db(&mut self) -> Result<()> {
+        for dir in [ETC_PATH, HOME_PATH] {
+            if self.lookup_entry(dir, false).is_err() {
+                self.as_root(|fs| fs.create_dir(dir))?;
+            }
+        }
+        if self.read_system_file(PASSWD_PATH)?.is_none() {
+            let content = format!("{}\n", User::root().to_line());
+            self.write_system_file(PASSWD_PATH, &content, "644")?;
+        }
+        if self.read_system_file(SHADOW_PATH)?.is_none() {
+            self.write_system_file(SHADOW_PATH, &format!("root:{}\n", LOCKED), "600")?;
+        }
+        Ok(())
+    }
+
+    /// Reads the shadow file into a map from user name to password hash.
+    fn read_shadow(&self) -> Result<HashMap<String, String>> {
+        let content = match self.read_system_file(SHADOW_PATH)? {
+            Some(content) => content,
+            None => format!("root:{}\n", LOCKED),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, hash)| (name.to_string(), hash.to_string()))
+            .collect())
+    }
+
+    fn write_shadow(&mut self, shadow: &HashMap<String, String>) -> Result<()> {
+        let mut lines = Vec::new();
+        for user in self.users()? {
+            let hash = shadow.get(&user.name).map_or(LOCKED, String::as_str);
+            lines.push(format!("{}:{}\n", user.name, hash));
+        }
+        self.write_system_file(SHADOW_PATH, &lines.concat(), "600")
+    }
+
+    /// Returns the account of `name`.
+    ///
+    /// # Errors
+    /// Returns `UserError::UserNotFound` if there is none.
+    #[trace_log]
+    pub fn user(&self, name: &str) -> Result<User> {
+        self.users()?
+            .into_iter()
+            .find(|user| user.name == name)
+            .ok_or(UserError::UserNotFound(name.to_string()).into())
+    }
+
+    /// Tells whether `name` can't log in yet because it has no password.
+    ///
+    /// # Errors
+    /// Returns `UserError::UserNotFound` if there is no such account.
+    #[trace_log]
+    pub fn is_locked(&self, name: &str) -> Result<bool> {
+        let user = self.user(name)?;
+        let shadow = self.read_shadow()?;
+        Ok(shadow
+            .get(&user.name)
+            .is_none_or(|hash| PasswordHash::new(hash).is_err()))
+    }
+
+    /// Returns the account the current credentials belong to, if any.
+    #[trace_log]
+    pub fn current_user(&self) -> Result<Option<User>> {
+        Ok(self
+            .users()?
+            .into_iter()
+            .find(|user| user.uid == self.credentials.uid))
+    }
+}
+
+impl Users for FileSystem {
+    /// Lists every account, a volume without a user database only has `root`.
+    #[trace_log]
+    fn users(&self) -> Result<Vec<User>> {
+        match self.read_system_file(PASSWD_PATH)? {
+            Some(content) => Ok(content.lines().filter_map(User::from_line).collect()),
+            None => Ok(vec![User::root()]),
+        }
+    }
+
+    /// Creates an account with the next free id, a group of the same id and a home directory.
+    ///
+    /// The account is locked until it gets a password.
+    /// Only the superuser may add accounts.
+    #[trace_log]
+    fn add_user(&mut self, name: &str) -> Result<User> {
+        if !self.credentials.is_root() {
+            return Err(FileError::NotPermitted(name.to_string()).into());
+        }
+        check_username(name)?;
+
+        self.ensure_user_db()?;
+        let mut users = self.users()?;
+        if users.iter().any(|user| user.name == name) {
+            return Err(UserError::UserExists(name.to_string()).into());
+        }
+
+        let uid = users
+            .iter()
+            .map(|user| user.uid + 1)
+            .max()
+            .unwrap_or(FIRST_USER_ID)
+            .max(FIRST_USER_ID);
+        let user = User {
+            name: name.to_string(),
+            uid,
+            gid: uid,
+            home: format!("{}/{}", HOME_PATH, name),
+        };
+
+        users.push(user.clone());
+        let passwd = users
+            .iter()
+            .map(|user| user.to_line() + "\n")
+            .collect::<String>();
+        self.write_system_file(PASSWD_PATH, &passwd, "644")?;
+        let mut shadow = self.read_shadow()?;
+        shadow.insert(name.to_string(), LOCKED.to_string());
+        self.write_shadow(&shadow)?;
+
+        if self.lookup_entry(&user.home, false).is_err() {
+            self.create_dir(&user.home)?;
+        }
+        self.change_owner(&user.home, Some(user.uid), Some(user.gid))?;
+
+        Ok(user)
+    }
+
+    /// Sets the password of `name`, which the superuser may do for everyone
+    /// and every other user only for themselves.
+    #[trace_log]
+    fn set_password(&mut self, name: &str, password: &str) -> Result<()> {
+        let user = self.user(name)?;
+        if !self.credentials.is_root() && self.credentials.uid != user.uid {
+            return Err(FileError::NotPermitted(name.to_string()).into());
+        }
+
+        self.ensure_user_db()?;
+        let mut shadow = self.read_shadow()?;
+        shadow.insert(user.name, hash_password(password)?);
+        self.write_shadow(&shadow)
+    }
+
+    /// Checks the password of `name` and returns the account.
+    ///
+    /// # Errors
+    /// Returns `UserError::AuthenticationFailed` if the password is wrong or the account is locked.
+    #[trace_log]
+    fn authenticate(&self, name: &str, password: &str) -> Result<User> {
+        let user = self
+            .user(name)
+            .map_err(|_| UserError::AuthenticationFailed(name.to_string()))?;
+        let shadow = self.read_shadow()?;
+        match shadow.get(name) {
+            Some(hash) if verify_password(hash, password) => Ok(user),
+            _ => Err(UserError::AuthenticationFailed(name.to_string()).into()),
+        }
+    }
+
+    /// Acts as `name` from now on, a password is only needed if the caller isn't the superuser.
+    #[trace_log]
+    fn switch_user(&mut self, name: &str, password: Option<&str>) -> Result<User> {
+        let user = if self.credentials.is_root() {
+            self.user(name)?
+        } else {
+            self.authenticate(name, password.unwrap_or_default())?
+        };
+        self.credentials = user.credentials();
+        Ok(user)
+    }
+
+    /// Starts a session as `name` in its home directory, the password is always checked.
+    #[trace_log]
+    fn login(&mut self, name: &str, password: &str) -> Result<User> {
+        let user = self.authenticate(name, password)?;
+        self.credentials = user.credentials();
+        if self.change_dir(&user.home).is_err() {
+            self.change_dir("/")?;
+        }
+        Ok(user)
+    }
+}
@@ -3,11 +3,19 @@ use log::debug;
 
 use logger_macro::trace_log;
 use rustic_disk::traits::BlockStorage;
+use rustic_disk::Disk;
 
-use crate::dir_entry::{DirBlock, DirEntry};
-use crate::errors::FileError;
-use crate::utils::{check_access_level, fixed_str, path_handler};
-use crate::{FileSystem, READ, ROOT_BLK};
+use crate::dir_entry::{bucket_of, DirBlock, DirEntry, FileType};
+use crate::errors::{FSError, FileError};
+use crate::fat::{FatType, FAT};
+use crate::format::DirIndex;
+use crate::utils::fixed_str::FixedString;
+use crate::utils::{fixed_str, path_handler};
+use crate::{FileSystem, ROOT_BLK};
+
+/// How many blocks a hashed directory may grow by to fit a single entry,
+/// names whose hashes keep colliding could otherwise take up the whole volume.
+const MAX_DIR_GROWTH: usize = 8;
 
 impl FileSystem {
     #[trace_log]
@@ -16,179 +24,375 @@ impl FileSystem {
             return Err(FileError::NotADirectory(entry.clone().name).into());
         }
 
-        let block_num = entry.blk_num;
-        let mut block = self.disk.read_block::<DirBlock>(block_num as usize)?;
+        Self::read_dir_chain(&self.disk, &self.fat, entry)
+    }
 
-        block.parent_entry = entry.clone();
-        block.blk_num = block_num;
+    /// Reads every block of a directory by following its FAT chain.
+    ///
+    /// Each block holds `FileSystem::num_entries()` entries,
+    /// the entries of all blocks are concatenated in chain order.
+    #[trace_log]
+    pub(crate) fn read_dir_chain(disk: &Disk, fat: &FAT, entry: &DirEntry) -> anyhow::Result<DirBlock> {
+        let blocks = Self::dir_chain(fat, entry)?;
+        let mut entries = Vec::with_capacity(blocks.len() * Self::num_entries());
+        for blk in &blocks {
+            let mut chunk = disk.read_block::<DirBlock>(*blk as usize)?.entries;
+            chunk.resize(Self::num_entries(), DirEntry::default());
+            entries.extend(chunk);
+        }
 
+        let mut block = DirBlock {
+            path: "".to_string(),
+            parent_entry: entry.clone(),
+            blk_num: entry.blk_num,
+            blocks,
+            index: fat.options.dir_index,
+            bucket: None,
+            entries,
+        };
+        block.join_long_names();
+        Ok(block)
+    }
+
+    /// Returns the blocks of a directory in chain order, only the FAT is read.
+    fn dir_chain(fat: &FAT, entry: &DirEntry) -> anyhow::Result<Vec<u16>> {
+        let mut blocks = vec![entry.blk_num];
+        while let Some(&FatType::Taken(next_blk)) = fat.get(*blocks.last().unwrap() as usize) {
+            if blocks.contains(&next_blk) {
+                return Err(FSError::InvalidBlockReference.into());
+            }
+            blocks.push(next_blk);
+        }
+        Ok(blocks)
+    }
+
+    /// Reads the part of a directory an entry named `name` can live in.
+    ///
+    /// For a hashed directory that is the one bucket block the name hashes to,
+    /// which is all a lookup, an insert or a removal of that name needs.
+    /// The bucket can be changed and written back with `write_dir_block`,
+    /// `add_dir_entry` reads the whole directory once the bucket has no room left.
+    /// A linear directory is read whole.
+    #[trace_log]
+    pub(crate) fn read_dir_bucket(&self, entry: &DirEntry, name: &FixedString) -> anyhow::Result<DirBlock> {
+        if entry.file_type != FileType::Directory {
+            return Err(FileError::NotADirectory(entry.clone().name).into());
+        }
+        if self.fat.options.dir_index != DirIndex::Hashed {
+            return self.read_dir_block(entry);
+        }
+
+        let blocks = Self::dir_chain(&self.fat, entry)?;
+        let bucket = bucket_of(name, blocks.len());
+        let blk = blocks[bucket];
+        let mut entries = self.disk.read_block::<DirBlock>(blk as usize)?.entries;
+        entries.resize(Self::num_entries(), DirEntry::default());
+
+        let mut block = DirBlock {
+            path: "".to_string(),
+            parent_entry: entry.clone(),
+            blk_num: entry.blk_num,
+            blocks: vec![blk],
+            index: DirIndex::Hashed,
+            bucket: Some(bucket),
+            entries,
+        };
+        // a name never spans two buckets, so its continuation slots are in the same block
+        block.join_long_names();
         Ok(block)
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     #[trace_log]
     pub fn write_dir_block(&self, block: &DirBlock) -> anyhow::Result<()> {
-        self.disk.write_block(block.blk_num as usize, block)?;
+        for (blk, chunk) in Self::dir_block_chunks(block)? {
+            self.disk.write_block(blk as usize, &chunk)?;
+        }
         Ok(())
     }
 
     #[cfg(target_arch = "wasm32")]
     pub fn write_dir_block(&mut self, block: &DirBlock) -> anyhow::Result<()> {
-        self.disk.write_block(block.blk_num as usize, block)?;
+        for (blk, chunk) in Self::dir_block_chunks(block)? {
+            self.disk.write_block(blk as usize, &chunk)?;
+        }
         Ok(())
     }
 
+    /// Splits the entries of a directory into the chunks stored in each of its blocks.
+    ///
+    /// Long names are spread over their continuation slots before splitting.
+    fn dir_block_chunks(block: &DirBlock) -> anyhow::Result<Vec<(u16, Vec<DirEntry>)>> {
+        let blocks = if block.blocks.is_empty() {
+            vec![block.blk_num]
+        } else {
+            block.blocks.clone()
+        };
+
+        if block.entries.len() > blocks.len() * Self::num_entries() {
+            return Err(FileError::DirectoryFull.into());
+        }
+
+        let slots = block.split_long_names();
+        Ok(blocks
+            .iter()
+            .zip(slots.chunks(Self::num_entries()))
+            .map(|(blk, chunk)| (*blk, chunk.to_vec()))
+            .collect())
+    }
+
+    /// Adds an entry to a directory, chaining a new block to it through the FAT if it is full.
+    ///
+    /// The directory still has to be written back with `write_dir_block` afterwards.
+    /// If the entry can't be added, the blocks linked for it are freed again.
+    ///
+    /// # Errors
+    /// Returns `FSError::NoFreeBlocks` if the directory is full and the disk is out of space
+    /// and `FileError::DirectoryFull` if a hashed directory would have to grow
+    /// by more than `MAX_DIR_GROWTH` blocks to fit the entry.
+    #[trace_log]
+    pub fn add_dir_entry(&mut self, block: &mut DirBlock, entry: DirEntry) -> anyhow::Result<()> {
+        if block.bucket.is_some() && !block.has_free_slot(&entry.name) {
+            // growing rehashes every bucket
+            let path = std::mem::take(&mut block.path);
+            *block = self.read_dir_block(&block.parent_entry)?;
+            block.path = path;
+        }
+
+        let (blocks, entries) = (block.blocks.clone(), block.entries.clone());
+        let max_blocks = blocks.len().max(1) + MAX_DIR_GROWTH;
+
+        while !block.has_free_slot(&entry.name) {
+            if let Err(e) = self.extend_dir_block(block, max_blocks) {
+                let last_blk = *blocks.last().unwrap_or(&block.blk_num);
+                if let Some(&FatType::Taken(next_blk)) = self.fat.get(last_blk as usize) {
+                    self.clear_file_data(next_blk)?;
+                }
+                self.update_fat(last_blk, None)?;
+                block.blocks = blocks;
+                block.entries = entries;
+                return Err(e);
+            }
+        }
+        block.add_entry(entry)
+    }
+
+    /// Appends a new, empty block to the FAT chain of a directory.
+    ///
+    /// Hashed directories are rehashed over the new number of buckets,
+    /// more blocks are added in the unlikely case that a bucket still overflows,
+    /// up to `max_blocks` in total.
+    #[trace_log]
+    fn extend_dir_block(&mut self, block: &mut DirBlock, max_blocks: usize) -> anyhow::Result<()> {
+        if block.blocks.is_empty() {
+            block.blocks.push(block.blk_num);
+        }
+
+        loop {
+            if block.blocks.len() >= max_blocks {
+                return Err(FileError::DirectoryFull.into());
+            }
+            let new_blk = self.get_free_block()?;
+            let last_blk = *block.blocks.last().unwrap();
+            self.set_fat_block(last_blk, FatType::Taken(new_blk))?;
+            self.update_fat(new_blk, None)?;
+
+            block.blocks.push(new_blk);
+            block
+                .entries
+                .resize(block.blocks.len() * Self::num_entries(), DirEntry::default());
+
+            if block.rehash() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Writes a parent block after a new entry was added to it
+    /// and adds the size of that entry to every ancestor directory.
     #[trace_log]
     pub fn update_dir(&mut self, block: &mut DirBlock, path: String) -> anyhow::Result<()> {
         let abs_path = path_handler::absolutize_from(&path, "/");
         let (parent, name) = path_handler::split_path(abs_path);
-        // iter over all parents and update their size and make sure that the new entry is added
-        let mut dirs = self.get_all_dirs(parent)?;
-        dirs.pop(); // remove the last one since we are going to update it
-        dirs.push(block.clone());
         let size_to_add = match block.get_entry(&name.clone().into()) {
             Some(entry) => entry.size,
             None => return Err(FileError::FileNotFound.into()),
         };
 
-        let mut dirs_iter = dirs.iter_mut().peekable();
-        while let Some(dir) = dirs_iter.next() {
-            if let Some(next_dir) = dirs_iter.peek() {
-                match dir.get_entry_mut(&next_dir.parent_entry.name) {
-                    Some(entry) => {
-                        entry.size += size_to_add;
-                    }
-                    None => {
-                        return Err(FileError::FileNotFound.into());
-                    }
+        self.write_dir_block(block)?;
+        self.update_dir_sizes(parent, size_to_add as i64)
+    }
+
+    /// Applies a signed size delta to the directory at `path` and all of its ancestors.
+    ///
+    /// The size of a directory is stored in the entry its parent holds for it,
+    /// so every directory on the path except the root gets `delta` added to its entry.
+    /// Every name of a file adds its size, the sizes don't tell the space taken on the volume.
+    /// The current directory block is reloaded afterwards so that it reflects the new sizes.
+    #[trace_log]
+    pub fn update_dir_sizes(&mut self, path: String, delta: i64) -> anyhow::Result<()> {
+        if delta != 0 {
+            // links may live in directories the caller can't search
+            let path = self.as_root(|fs| {
+                fs.resolve_path(&format!("/{}", path.trim_start_matches('/')), true)
+            })?;
+            let dirs = self.path_entries(&path)?;
+
+            for (dir, child) in dirs.iter().zip(dirs.iter().skip(1)) {
+                let mut bucket = self.read_dir_bucket(dir, &child.name)?;
+                match bucket.get_entry_mut(&child.name) {
+                    Some(entry) => entry.size = entry.size.saturating_add_signed(delta),
+                    None => return Err(FileError::FileNotFound.into()),
                 }
+                self.write_dir_block(&bucket)?;
             }
-
-            self.write_dir_block(dir)?;
         }
 
         // update working dir
         let cwd = self.curr_block.path.clone();
-        self.curr_block = self.traverse_dir(cwd)?;
+        self.curr_block = self.walk_dir(cwd)?;
 
         Ok(())
     }
 
+    /// Rebuilds the size of every directory from scratch by summing the sizes of its entries.
+    ///
+    /// Returns the total size of the file system,
+    /// in which a file with several names counts once per name.
     #[trace_log]
-    fn read_root_dir(&self) -> anyhow::Result<DirBlock> {
-        let root_entry = DirEntry::new(
-            fixed_str::FixedString::from("/"),
-            crate::dir_entry::FileType::Directory,
-            0,
-            ROOT_BLK as u16,
-        );
+    pub fn recompute_dir_sizes(&mut self) -> anyhow::Result<u64> {
+        let root = self.read_root_dir()?;
+        let total = self.recompute_block_sizes(root)?;
 
-        let mut root_block = self.read_dir_block(&root_entry)?;
-        root_block.path = "/".to_string();
+        let cwd = self.curr_block.path.clone();
+        self.curr_block = self.walk_dir(cwd)?;
 
-        Ok(root_block)
+        Ok(total)
     }
 
-    //#[trace_log]
-    pub fn change_dir(&mut self, path: &str) -> anyhow::Result<()> {
-        let abs_path = path_handler::absolutize_from(path, &self.curr_block.path);
-        let (parent, name) = path_handler::split_path(abs_path.clone());
-
-        if parent == "/" {
-            let root = self.read_root_dir()?;
+    fn recompute_block_sizes(&mut self, mut block: DirBlock) -> anyhow::Result<u64> {
+        let mut total = 0;
 
-            if name.is_empty() {
-                self.curr_block = root;
-                return Ok(());
+        for index in 0..block.entries.len() {
+            let entry = &block.entries[index];
+            if entry.name.is_empty() {
+                continue;
             }
 
-            let entry = root.get_entry(&name.clone().into());
-            match entry {
-                Some(entry) => {
-                    if entry.file_type != crate::dir_entry::FileType::Directory {
-                        return Err(FileError::NotADirectory(name.into()).into());
-                    }
-
-                    let mut block = self.read_dir_block(entry)?;
-                    let path_buf = std::path::PathBuf::from(&root.path);
-                    block.path = path_buf.join(&name).to_str().unwrap().to_string();
-
-                    self.curr_block = block;
-                }
-                None => {
-                    return Err(FileError::FileNotFound.into());
-                }
+            if entry.file_type == crate::dir_entry::FileType::Directory {
+                let child = self.read_dir_block(entry)?;
+                block.entries[index].size = self.recompute_block_sizes(child)?;
             }
 
-            return Ok(());
+            total += block.entries[index].size;
         }
 
-        let parent_block = self.traverse_dir(parent)?;
-
-        // Do we have read permission for the parent directory?
-        if !check_access_level(parent_block.parent_entry.access_level, READ) {
-            return Err(FileError::NoPermissionToRead(name).into());
-        }
+        self.write_dir_block(&block)?;
+        Ok(total)
+    }
 
-        match parent_block.get_entry(&name.clone().into()) {
-            Some(entry) => {
-                if entry.file_type != crate::dir_entry::FileType::Directory {
-                    return Err(FileError::NotADirectory(name.into()).into());
-                }
+    /// The entry of the root directory, which has no parent to store it.
+    pub(crate) fn root_entry() -> DirEntry {
+        DirEntry::new(
+            fixed_str::FixedString::from("/"),
+            FileType::Directory,
+            0,
+            ROOT_BLK as u16,
+        )
+    }
 
-                let mut new_block = self.read_dir_block(entry)?;
-                let path_buf = std::path::PathBuf::from(&parent_block.path);
-                new_block.path = path_buf.join(&name).to_str().unwrap().to_string();
+    #[trace_log]
+    pub(crate) fn read_root_dir(&self) -> anyhow::Result<DirBlock> {
+        let mut root_block = self.read_dir_block(&Self::root_entry())?;
+        root_block.path = "/".to_string();
 
-                self.curr_block = new_block;
-            }
-            None => return Err(FileError::FileNotFound.into()),
-        };
+        Ok(root_block)
+    }
 
+    /// Makes the directory at `path` the current one, which needs search permission on it.
+    //#[trace_log]
+    pub fn change_dir(&mut self, path: &str) -> anyhow::Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        self.curr_block = self.traverse_dir(abs_path)?;
         Ok(())
     }
 
+    /// Reads the directory at `path`, following any symbolic links on the way.
+    ///
+    /// # Errors
+    /// Returns `FileError::NoPermissionToExecute` unless the caller may search
+    /// the directory and every directory above it.
     #[trace_log]
     pub(crate) fn traverse_dir(&self, path: String) -> anyhow::Result<DirBlock> {
-        let names = path
-            .split('/')
-            .filter(|&c| !c.is_empty())
-            .collect::<Vec<&str>>();
-        #[cfg(feature = "debug")]
-        {
-            debug!("Traversing path: {:?}", names)
-        }
-        let mut block = self.read_root_dir()?; // start from root since fuck being efficent :)
+        // the path is already absolute, even without a leading slash
+        let path = self.resolve_path(&format!("/{}", path.trim_start_matches('/')), true)?;
+        let block = self.walk_dir(path)?;
+        self.check_search(&block.parent_entry)?;
+        Ok(block)
+    }
 
-        for name in names {
-            let entry = block.get_entry(&name.into());
-            match entry {
-                Some(entry) => {
-                    if entry.file_type != crate::dir_entry::FileType::Directory {
-                        return Err(FileError::NotADirectory(name.into()).into());
-                    }
+    /// Reads the part of the directory at `path` an entry named `name` can live in,
+    /// with the same checks as `traverse_dir`.
+    ///
+    /// See `read_dir_bucket` for what the returned block holds.
+    #[trace_log]
+    pub(crate) fn traverse_bucket(&self, path: String, name: &str) -> anyhow::Result<DirBlock> {
+        // the path is already absolute, even without a leading slash
+        let path = self.resolve_path(&format!("/{}", path.trim_start_matches('/')), true)?;
+        let dir = self.dir_entry_at(&path)?;
+        self.check_search(&dir)?;
+        let mut block = self.read_dir_bucket(&dir, &name.into())?;
+        block.path = path;
+        Ok(block)
+    }
 
-                    let mut new_block = self.read_dir_block(entry)?;
-                    let path_buf = std::path::PathBuf::from(&block.path);
-                    new_block.path = path_buf.join(name).to_str().unwrap().to_string();
-                    #[cfg(feature = "debug")]
-                    {
-                        debug!("New block: {:?}", new_block);
-                    }
+    /// Reads the directory at `path`, which must not contain any symbolic links.
+    ///
+    /// No permissions are checked, this is meant for paths that were already resolved.
+    #[trace_log]
+    pub(crate) fn walk_dir(&self, path: String) -> anyhow::Result<DirBlock> {
+        let dir = self.dir_entry_at(&path)?;
+        let mut block = self.read_dir_block(&dir)?;
+        block.path = format!("/{}", path.trim_start_matches('/'));
+        Ok(block)
+    }
 
-                    block = new_block;
-                }
-                None => {
-                    return Err(FileError::FileNotFound.into());
+    /// Returns the entry of the directory at `path`, which must not contain any symbolic links.
+    pub(crate) fn dir_entry_at(&self, path: &str) -> anyhow::Result<DirEntry> {
+        let mut entries = self.path_entries(path)?;
+        Ok(entries.pop().unwrap_or_else(Self::root_entry))
+    }
+
+    /// Returns the entry of every directory on `path`, starting with the root,
+    /// only the bucket of each name on the way is read.
+    fn path_entries(&self, path: &str) -> anyhow::Result<Vec<DirEntry>> {
+        let mut entries = vec![Self::root_entry()];
+        for name in path.split('/').filter(|&c| !c.is_empty()) {
+            #[cfg(feature = "debug")]
+            {
+                debug!("Traversing: {}", name)
+            }
+            let name = FixedString::from(name);
+            let bucket = self.read_dir_bucket(entries.last().unwrap(), &name)?;
+            match bucket.get_entry(&name) {
+                Some(entry) if entry.file_type == FileType::Directory => {
+                    entries.push(entry.clone())
                 }
+                Some(_) => return Err(FileError::NotADirectory(name).into()),
+                None => return Err(FileError::FileNotFound.into()),
             }
         }
-
-        Ok(block)
+        Ok(entries)
     }
 
+    /// Reads the directory at `path` and every directory above it, starting with the root.
+    ///
+    /// # Errors
+    /// Returns `FileError::NoPermissionToExecute` unless the caller may search all of them.
     #[trace_log]
     pub fn get_all_dirs(&self, path: String) -> anyhow::Result<Vec<DirBlock>> {
+        // the path is already absolute, even without a leading slash
+        let path = self.resolve_path(&format!("/{}", path.trim_start_matches('/')), true)?;
         let names = path
             .split('/')
             .filter(|&c| !c.is_empty())
@@ -225,17 +429,20 @@ impl FileSystem {
                 }
             }
         }
+        self.check_search(&block.parent_entry)?;
 
         Ok(blocks)
     }
 
+    /// Returns the absolute path of the current directory.
+    #[trace_log]
+    pub fn cwd(&self) -> String {
+        path_handler::anchor(&self.curr_block.path)
+    }
+
     #[trace_log]
     pub fn print_working_dir(&mut self) -> anyhow::Result<()> {
-        let path = if self.curr_block.path.is_empty() {
-            "/".to_string()
-        } else {
-            self.curr_block.path.clone()
-        };
+        let path = self.cwd();
         self.io_handler.write(path)?;
         Ok(())
     }
@@ -7,9 +7,17 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(feature = "py-bindings")]
 use pyo3::prelude::*;
 
+/// The longest name, in bytes, that an entry can have.
+pub const MAX_NAME_LEN: usize = 255;
+
+/// The number of name bytes stored in a single directory slot.
+///
+/// Longer names continue in the slots following the entry, see `DirBlock`.
+pub const NAME_CHUNK_LEN: usize = 55;
+
 #[derive(Debug, Clone, PartialEq, thiserror::Error, Serialize, Deserialize)]
 enum NameError {
-    #[error("Name too long: found {0}, max length is 56 including null terminator.")]
+    #[error("Name too long: found {0}, max length is 255 bytes.")]
     NameTooLong(usize),
     #[error("Invalid name: {0}")]
     InvalidName(String),
@@ -21,15 +29,19 @@ pub struct FixedString {
     pub value: String,
 }
 
+/// Conversions don't check the length so that looking up an overly long name
+/// simply finds nothing, names of new entries are checked with `utils::check_name`.
 impl From<String> for FixedString {
     fn from(value: String) -> Self {
-        FixedString::new(value).unwrap()
+        FixedString { value }
     }
 }
 
 impl From<&str> for FixedString {
     fn from(value: &str) -> Self {
-        FixedString::new(value.to_owned()).unwrap()
+        FixedString {
+            value: value.to_owned(),
+        }
     }
 }
 
@@ -41,7 +53,7 @@ impl Display for FixedString {
 
 impl FixedString {
     pub(crate) fn new(value: String) -> anyhow::Result<Self> {
-        if value.len() > 56 {
+        if value.len() > MAX_NAME_LEN {
             return Err(NameError::NameTooLong(value.len()).into());
         }
 
@@ -51,6 +63,32 @@ impl FixedString {
     pub fn is_empty(&self) -> bool {
         self.value.is_empty()
     }
+
+    /// Splits the name into the pieces stored in each directory slot.
+    ///
+    /// Every piece is at most `NAME_CHUNK_LEN` bytes and ends on a character boundary,
+    /// so each of them is valid UTF-8 on its own. An empty name has no pieces.
+    pub(crate) fn chunks(&self) -> Vec<&str> {
+        let mut chunks = Vec::new();
+        let mut rest = self.value.as_str();
+        while !rest.is_empty() {
+            let end = char_boundary(rest, NAME_CHUNK_LEN);
+            chunks.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        chunks
+    }
+}
+
+/// Returns the largest character boundary of `value` that is at most `max` bytes in.
+fn char_boundary(value: &str, max: usize) -> usize {
+    if value.len() <= max {
+        return value.len();
+    }
+    (0..=max)
+        .rev()
+        .find(|&i| value.is_char_boundary(i))
+        .unwrap_or(0)
 }
 
 impl Serialize for FixedString {
@@ -60,7 +98,7 @@ impl Serialize for FixedString {
     {
         let mut buffer = [0u8; 56];
         let bytes = self.value.as_bytes();
-        let length = bytes.len().min(55);
+        let length = char_boundary(&self.value, NAME_CHUNK_LEN);
         buffer[..length].copy_from_slice(&bytes[..length]);
         serializer.serialize_bytes(&buffer)
     }
@@ -0,0 +1,58 @@
+// Hex dumps of file content for the `hexdump` and `xxd` commands
+
+/// Bytes shown per line of a dump.
+const BYTES_PER_LINE: usize = 16;
+
+/// Returns `byte` as the character shown next to the hex values, `.` if it isn't printable ASCII.
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Formats `data` like `hexdump -C`, 16 bytes per line in two groups of 8 followed by
+/// their characters, and a last line holding the length.
+pub(crate) fn hexdump(data: &[u8]) -> String {
+    let mut output = String::new();
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in chunk.iter().enumerate() {
+            if i == BYTES_PER_LINE / 2 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x} ", byte));
+        }
+        let ascii = chunk.iter().map(|&b| printable(b)).collect::<String>();
+        output.push_str(&format!(
+            "{:08x}  {:<49} |{}|\n",
+            line * BYTES_PER_LINE,
+            hex,
+            ascii
+        ));
+    }
+    output.push_str(&format!("{:08x}", data.len()));
+    output
+}
+
+/// Formats `data` like `xxd`, 16 bytes per line in groups of 2 followed by their characters.
+pub(crate) fn xxd(data: &[u8]) -> String {
+    data.chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex = chunk
+                .chunks(2)
+                .map(|pair| {
+                    pair.iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk.iter().map(|&b| printable(b)).collect::<String>();
+            format!("{:08x}: {:<39}  {}", line * BYTES_PER_LINE, hex, ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -11,20 +11,33 @@ use crate::FileSystem;
 
 pub mod dirs;
 pub mod fixed_str;
+pub(crate) mod hex;
 pub(crate) mod path_handler;
-
-pub fn check_access_level(access_level: u8, required: u8) -> bool {
-    (access_level & required) == required
+pub(crate) mod side_blocks;
+
+/// Checks that `name` can be used as the name of a new entry.
+///
+/// # Errors
+/// Returns `FileError::FilenameTooLong` if the name is longer than `MAX_NAME_LEN` bytes
+/// and `FileError::InvalidFilename` if it is empty.
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    if name.len() > fixed_str::MAX_NAME_LEN {
+        Err(FileError::FilenameTooLong.into())
+    } else if name.is_empty() {
+        Err(FileError::InvalidFilename(name.to_string()).into())
+    } else {
+        Ok(())
+    }
 }
 
 impl FileSystem {
     /// The remove functon is used to delete a file from the current directory
     #[trace_log]
-    pub fn remove_entry(&mut self, name: &str) -> Result<()> {
-        let abs_path = absolutize_from(name, &self.curr_block.path);
+    pub fn remove_entry(&mut self, path: &str) -> Result<()> {
+        let abs_path = absolutize_from(path, &self.curr_block.path);
         let (parent, name) = path_handler::split_path(abs_path.clone());
 
-        let parent_block = self.traverse_dir(parent.clone())?;
+        let parent_block = self.traverse_bucket(parent.clone(), &name)?;
 
         let entry = parent_block
             .get_entry(&name.clone().into())
@@ -32,8 +45,8 @@ impl FileSystem {
             .clone();
 
         match entry.file_type {
-            FileType::File => self.delete_file(&name)?,
-            FileType::Directory => self.delete_dir(&name)?,
+            FileType::File | FileType::Symlink => self.delete_file(path)?,
+            FileType::Directory => self.delete_dir(path)?,
         }
         Ok(())
     }
@@ -53,6 +53,12 @@ pub fn absolutize_from(path: &str, cwd: &str) -> String {
     }
 }
 
+/// Turns a path from `absolutize_from`, which may lack the leading slash, into one starting at the root.
+#[trace_log]
+pub fn anchor(path: &str) -> String {
+    format!("/{}", path.trim_start_matches('/'))
+}
+
 #[trace_log]
 pub fn split_path(path: String) -> (String, String) {
     // To find the parent directory path
@@ -0,0 +1,72 @@
+// Storage for what an entry keeps outside its directory slot,
+// its access control list and its extended attributes.
+
+use anyhow::Result;
+use logger_macro::trace_log;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::dir_entry::DirEntry;
+use crate::errors::FSError;
+use crate::file_data::FileData;
+use crate::FileSystem;
+
+/// First block of a side chain an entry doesn't have,
+/// block 0 always holds the root directory so no chain can start there.
+pub(crate) const NO_SIDE_BLOCK: u16 = 0;
+
+impl FileSystem {
+    /// Reads the value stored in the side chain starting at `blk`, `None` if there is none.
+    pub(crate) fn read_side_block<T: DeserializeOwned>(&self, blk: u16) -> Result<Option<T>> {
+        if blk == NO_SIDE_BLOCK {
+            return Ok(None);
+        }
+        let data = self.read_file_data(blk)?;
+        Ok(Some(bincode::deserialize(&data.data)?))
+    }
+
+    /// Writes `value` to a new side chain and returns its first block.
+    pub(crate) fn write_side_block<T: Serialize>(&mut self, value: &T) -> Result<u16> {
+        let data = bincode::serialize(value).map_err(FSError::SerializationError)?;
+        let blk = self.get_free_block()?;
+        if let Err(e) = self.write_data(&FileData::from(data), blk) {
+            self.clear_file_data(blk)?;
+            return Err(e);
+        }
+        Ok(blk)
+    }
+
+    /// Frees the side chain starting at `blk`, if there is one.
+    #[trace_log]
+    pub(crate) fn release_side_block(&mut self, blk: u16) -> Result<()> {
+        if blk != NO_SIDE_BLOCK {
+            self.clear_file_data(blk)?;
+        }
+        Ok(())
+    }
+
+    /// Frees the access control list and the extended attributes of `entry`.
+    #[trace_log]
+    pub(crate) fn release_side_blocks(&mut self, entry: &DirEntry) -> Result<()> {
+        self.release_side_block(entry.acl_blk)?;
+        self.release_side_block(entry.xattr_blk)
+    }
+
+    /// Points the entry at `path` to the new side chain `new_blk` through `set`
+    /// and frees the chain `old_blk` it replaces.
+    ///
+    /// If the entry can't be updated, the new chain is freed instead.
+    pub(crate) fn replace_side_block(
+        &mut self,
+        path: &str,
+        old_blk: u16,
+        new_blk: u16,
+        set: impl Fn(&mut DirEntry),
+    ) -> Result<()> {
+        if let Err(e) = self.update_entry_at(path, set) {
+            self.release_side_block(new_blk)?;
+            return Err(e);
+        }
+        self.release_side_block(old_blk)
+    }
+}
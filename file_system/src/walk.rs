@@ -0,0 +1,249 @@
+use std::fmt;
+use std::vec;
+
+use anyhow::Result;
+
+use crate::dir_entry::DirBlock;
+use crate::info::EntryInfo;
+use crate::traits::Directory;
+use crate::FileSystem;
+
+/// The entries of one directory in listing order, returned by `Directory::read_dir`.
+///
+/// The slots of the directory are read when it is opened, the free ones are skipped.
+/// Each entry is only described when it is yielded, which is when the target of a symbolic link is read.
+#[derive(Clone)]
+pub struct ReadDir<'a> {
+    fs: &'a FileSystem,
+    block: DirBlock,
+    /// The used slots of `block` sorted by name.
+    slots: vec::IntoIter<usize>,
+}
+
+impl fmt::Debug for ReadDir<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadDir")
+            .field("path", &self.block.path)
+            .field("remaining", &self.slots.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> ReadDir<'a> {
+    pub(crate) fn new(fs: &'a FileSystem, block: DirBlock) -> Self {
+        let mut slots = (0..block.entries.len())
+            .filter(|&slot| !block.entries[slot].name.is_empty())
+            .collect::<Vec<_>>();
+        slots.sort_by(|&a, &b| {
+            block.entries[a]
+                .name
+                .value
+                .cmp(&block.entries[b].name.value)
+        });
+        ReadDir {
+            fs,
+            block,
+            slots: slots.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ReadDir<'_> {
+    type Item = EntryInfo;
+
+    fn next(&mut self) -> Option<EntryInfo> {
+        let slot = self.slots.next()?;
+        Some(self.fs.listed_entry_info(&self.block.entries[slot]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ReadDir<'_> {}
+
+/// An entry found by `Walk`, with its absolute path and its depth below the start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkEntry {
+    pub path: String,
+    /// `0` for the entry the walk started at, `1` for the entries in it and so on.
+    pub depth: usize,
+    pub info: EntryInfo,
+}
+
+/// A directory being walked, with the entries still to visit.
+struct Frame<'a> {
+    path: String,
+    depth: usize,
+    entries: ReadDir<'a>,
+    /// The directory itself while it waits to be yielded after its contents.
+    entry: Option<WalkEntry>,
+}
+
+/// A recursive walk over a directory tree, returned by `Directory::walk` and set up like `walkdir`.
+///
+/// The entry the walk starts at is yielded first at depth 0, then the tree below it
+/// with every directory before its contents unless `contents_first` is set.
+/// Symbolic links are yielded but never followed, except for the start.
+/// A directory that can't be read is still yielded and followed by its error.
+///
+/// ```no_run
+/// # use file_system::prelude::*;
+/// # fn main() -> anyhow::Result<()> {
+/// let fs = FileSystem::new(Box::new(StdIOHandler))?;
+/// for entry in fs.walk("/").min_depth(1).max_depth(2).filter_entry(|e| e.info.name != "tmp") {
+///     println!("{}", entry?.path);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Walk<'a> {
+    fs: &'a FileSystem,
+    start: Option<String>,
+    min_depth: usize,
+    max_depth: usize,
+    contents_first: bool,
+    filter: Box<dyn FnMut(&WalkEntry) -> bool + 'a>,
+    stack: Vec<Frame<'a>>,
+    error: Option<anyhow::Error>,
+}
+
+impl fmt::Debug for Walk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Walk")
+            .field("start", &self.start)
+            .field("min_depth", &self.min_depth)
+            .field("max_depth", &self.max_depth)
+            .field("contents_first", &self.contents_first)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(fs: &'a FileSystem, path: &str) -> Self {
+        Walk {
+            fs,
+            start: Some(path.to_string()),
+            min_depth: 0,
+            max_depth: usize::MAX,
+            contents_first: false,
+            filter: Box::new(|_| true),
+            stack: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Only yields entries at least `depth` levels below the start, the tree above is still walked.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Doesn't descend more than `depth` levels below the start, `0` only yields the start.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Yields every directory after its contents instead of before them.
+    pub fn contents_first(mut self, contents_first: bool) -> Self {
+        self.contents_first = contents_first;
+        self
+    }
+
+    /// Only yields the entries `filter` accepts,
+    /// a directory it rejects is skipped together with everything below it.
+    pub fn filter_entry(mut self, filter: impl FnMut(&WalkEntry) -> bool + 'a) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    /// Looks up the entry the walk starts at, a symbolic link is followed.
+    fn start_entry(&self, path: &str) -> Result<WalkEntry> {
+        let path = self.fs.resolve_path(path, true)?;
+        let entry = self.fs.lookup_entry(&path, false)?;
+        Ok(WalkEntry {
+            path,
+            depth: 0,
+            info: self.fs.entry_info(&entry)?,
+        })
+    }
+
+    /// Filters `entry`, opens it if it is a directory to descend into and returns what to yield now.
+    fn visit(&mut self, entry: WalkEntry) -> Option<Result<WalkEntry>> {
+        if !(self.filter)(&entry) {
+            return None;
+        }
+
+        if entry.info.is_dir() && entry.depth < self.max_depth {
+            match self.fs.read_dir(&entry.path) {
+                Ok(entries) => {
+                    self.stack.push(Frame {
+                        path: entry.path.clone(),
+                        depth: entry.depth,
+                        entries,
+                        entry: self.contents_first.then(|| entry.clone()),
+                    });
+                    if self.contents_first {
+                        return None;
+                    }
+                }
+                Err(e) if entry.depth >= self.min_depth => {
+                    self.error = Some(e);
+                    return Some(Ok(entry));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        (entry.depth >= self.min_depth).then_some(Ok(entry))
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Result<WalkEntry>> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        if let Some(path) = self.start.take() {
+            match self.start_entry(&path) {
+                Ok(entry) => {
+                    if let Some(item) = self.visit(entry) {
+                        return Some(item);
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        while let Some(frame) = self.stack.last_mut() {
+            match frame.entries.next() {
+                Some(info) => {
+                    let path = match frame.path.as_str() {
+                        "/" => format!("/{}", info.name),
+                        parent => format!("{}/{}", parent, info.name),
+                    };
+                    let entry = WalkEntry {
+                        path,
+                        depth: frame.depth + 1,
+                        info,
+                    };
+                    if let Some(item) = self.visit(entry) {
+                        return Some(item);
+                    }
+                }
+                None => {
+                    let frame = self.stack.pop()?;
+                    if let Some(entry) = frame.entry.filter(|e| e.depth >= self.min_depth) {
+                        return Some(Ok(entry));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use logger_macro::trace_log;
+
+use crate::dir_entry::DirEntry;
+use crate::errors::FileError;
+use crate::traits::XattrHandling;
+use crate::utils::side_blocks::NO_SIDE_BLOCK;
+use crate::{FileSystem, READ, WRITE};
+
+/// `xattr_blk` of an entry without extended attributes.
+pub(crate) const NO_XATTRS: u16 = NO_SIDE_BLOCK;
+/// Longest name of an extended attribute in bytes.
+pub const XATTR_NAME_MAX: usize = 255;
+/// Largest value of an extended attribute in bytes.
+pub const XATTR_VALUE_MAX: usize = 64 * 1024;
+
+/// Extended attributes of an entry by name, kept sorted so they list in a stable order.
+type Xattrs = BTreeMap<String, Vec<u8>>;
+
+/// Checks that `name` can be used as the name of an extended attribute.
+///
+/// # Errors
+/// Returns `FileError::InvalidXattr` if it is empty or longer than `XATTR_NAME_MAX` bytes.
+fn check_xattr_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return Err(FileError::InvalidXattr(name.to_string()).into());
+    }
+    Ok(())
+}
+
+impl FileSystem {
+    /// Reads the extended attributes of `entry`, empty if it has none.
+    #[trace_log]
+    fn read_xattrs(&self, entry: &DirEntry) -> Result<Xattrs> {
+        Ok(self.read_side_block(entry.xattr_blk)?.unwrap_or_default())
+    }
+
+    /// Writes `xattrs` to a new chain of blocks and returns its first block,
+    /// no attributes need no block.
+    #[trace_log]
+    fn write_xattrs(&mut self, xattrs: &Xattrs) -> Result<u16> {
+        if xattrs.is_empty() {
+            return Ok(NO_XATTRS);
+        }
+        self.write_side_block(xattrs)
+    }
+
+    /// Writes a copy of the extended attributes of `entry` and returns its first block.
+    #[trace_log]
+    pub(crate) fn copy_xattrs(&mut self, entry: &DirEntry) -> Result<u16> {
+        let xattrs = self.read_xattrs(entry)?;
+        self.write_xattrs(&xattrs)
+    }
+
+    /// Applies `update` to the extended attributes of the entry at `path` and writes them back,
+    /// the caller needs write permission on the entry.
+    fn update_xattrs(
+        &mut self,
+        path: &str,
+        update: impl FnOnce(&mut Xattrs) -> Result<()>,
+    ) -> Result<()> {
+        let abs_path = self.resolve_path(path, true)?;
+        let entry = self.lookup_entry(&abs_path, false)?;
+        if abs_path == "/" {
+            return Err(FileError::NotPermitted(abs_path).into());
+        }
+        self.check_access(&entry, WRITE)?;
+
+        let mut xattrs = self.read_xattrs(&entry)?;
+        update(&mut xattrs)?;
+        let xattr_blk = self.write_xattrs(&xattrs)?;
+        self.replace_side_block(&abs_path, entry.xattr_blk, xattr_blk, |entry| {
+            entry.xattr_blk = xattr_blk
+        })
+    }
+}
+
+impl XattrHandling for FileSystem {
+    /// Returns the value of the extended attribute `name` of the entry at `path`.
+    ///
+    /// # Errors
+    /// Returns `FileError::XattrNotFound` if the entry has no such attribute.
+    #[trace_log]
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        let entry = self.lookup_entry(path, true)?;
+        self.check_access(&entry, READ)?;
+        self.read_xattrs(&entry)?
+            .remove(name)
+            .ok_or(FileError::XattrNotFound(name.to_string()).into())
+    }
+
+    /// Sets the extended attribute `name` of the entry at `path` to `value`,
+    /// replacing any value it had.
+    ///
+    /// The root directory can't hold attributes.
+    #[trace_log]
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        check_xattr_name(name)?;
+        if value.len() > XATTR_VALUE_MAX {
+            return Err(FileError::InvalidXattr(name.to_string()).into());
+        }
+        self.update_xattrs(path, |xattrs| {
+            xattrs.insert(name.to_string(), value.to_vec());
+            Ok(())
+        })
+    }
+
+    /// Lists the names of the extended attributes of the entry at `path` in sorted order.
+    #[trace_log]
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        let entry = self.lookup_entry(path, true)?;
+        self.check_access(&entry, READ)?;
+        Ok(self.read_xattrs(&entry)?.into_keys().collect())
+    }
+
+    /// Removes the extended attribute `name` of the entry at `path`.
+    ///
+    /// # Errors
+    /// Returns `FileError::XattrNotFound` if the entry has no such attribute.
+    #[trace_log]
+    fn remove_xattr(&mut self, path: &str, name: &str) -> Result<()> {
+        self.update_xattrs(path, |xattrs| match xattrs.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(FileError::XattrNotFound(name.to_string()).into()),
+        })
+    }
+}
@@ -1,5 +1,5 @@
 use crate::components::output::Output;
-use crate::{read_all, GlobalState};
+use crate::GlobalState;
 use file_system::prelude::*;
 use leptos::ev::Event;
 use leptos::ev::SubmitEvent;
@@ -14,8 +14,9 @@ use web_sys::HtmlInputElement;
 
 // Adjusted `ls` function based on the provided mock-up
 pub fn ls(fs: &mut FileSystem) -> Result<Vec<String>, String> {
-    match fs.list_dir().map_err(|e| e.to_string()) {
-        Ok(_) => println!("Listing directory contents..."),
+    let cwd = fs.cwd();
+    let entries = match fs.read_dir(&cwd).map_err(|e| e.to_string()) {
+        Ok(entries) => entries,
         Err(e) => {
             let error = format!("Failed to list directory contents: {}", e);
             eprintln!("{}", error);
@@ -23,9 +24,13 @@ pub fn ls(fs: &mut FileSystem) -> Result<Vec<String>, String> {
         }
     };
 
-    let io_handler = fs.io_handler.as_mut();
-
-    let output = read_all(io_handler);
+    let output = entries
+        .into_iter()
+        .map(|entry| match entry.target {
+            Some(target) => format!("{} -> {}", entry.name, target),
+            None => entry.name,
+        })
+        .collect::<Vec<_>>();
     #[cfg(debug_assertions)]
     debug!("ls output: {:?}", output);
     Ok(output)
@@ -55,8 +60,8 @@ impl InputConstructor for InputHandler {
 }
 
 impl Input for InputHandler {
-    fn read_lines(&mut self) -> anyhow::Result<String> {
-        Ok(self.data.clone())
+    fn read_data(&mut self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.data.clone().into_bytes())
     }
 }
 
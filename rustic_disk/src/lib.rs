@@ -497,7 +497,7 @@ mod tests {
     fn disk_creation_does_not_overwrite_existing_file() {
         let _ = fs::remove_file(DISKNAME);
         let data = setup_data();
-        let mut disk = Disk::new().unwrap();
+        let disk = Disk::new().unwrap();
         disk.write_block(0, &data).unwrap();
         assert_eq!(data, disk.read_block::<TestData>(0).unwrap());
         //let _ = fs::remove_file(DISKNAME);
@@ -505,7 +505,7 @@ mod tests {
 
     #[test]
     fn write_block_writes_correct_data() {
-        let mut disk = Disk::new().unwrap();
+        let disk = Disk::new().unwrap();
         let write_result = disk.write_block(0, &"new data");
         assert!(write_result.is_ok());
         let read_result: Result<String, _> = disk.read_block(0);
@@ -516,7 +516,7 @@ mod tests {
 
     #[test]
     fn write_block_returns_error_if_data_exceeds_block_size() {
-        let mut disk = Disk::new().unwrap();
+        let disk = Disk::new().unwrap();
         let large_data = "a".repeat(Disk::BLOCK_SIZE + 1);
         let result = disk.write_block(0, &large_data);
         assert!(result.is_err());
@@ -22,6 +22,11 @@ enum ShellError {
     #[error("Invalid command usage")]
     InvalidUsage,
 
+    /// # Password mismatch
+    /// Error indicating that the two entries of a new password differ.
+    #[error("Passwords do not match")]
+    PasswordMismatch,
+
     /// # File system error
     /// Represents an error stemming from file system operations.
     ///
@@ -47,6 +52,10 @@ pub struct Shell {
     /// commands that involve file and directory operations. It's encapsulated within
     /// the shell to centralize file system access and error handling.
     file_system: FileSystem,
+
+    /// # previous_credentials
+    /// The credentials to return to on `exit`, one for every `su` that is still active.
+    previous_credentials: Vec<Credentials>,
 }
 
 /// `command_handler` is a macro that takes four arguments:
@@ -129,11 +138,38 @@ impl Shell {
     pub fn new() -> Result<Shell> {
         trace!("Starting shell...");
         let io_handler = Box::new(StdIOHandler); // This is a mock input handler
+        let mut file_system = FileSystem::new(io_handler)?;
+        // a locked root could never be unlocked by anyone else,
+        // so a new volume is set up and root gets a password before anyone logs in
+        if !file_system.is_formatted() {
+            file_system.format()?;
+        }
+        if file_system.is_locked("root")? {
+            Self::set_root_password(&mut file_system)?;
+        }
+        // nothing runs as the superuser until someone logs in as root
+        file_system.set_credentials(Credentials::nobody());
         Ok(Shell {
-            file_system: FileSystem::new(io_handler)?,
+            file_system,
+            previous_credentials: Vec::new(),
         })
     }
 
+    /// Asks for the first password of root, while the shell still acts as the superuser.
+    fn set_root_password(file_system: &mut FileSystem) -> Result<()> {
+        println!("root has no password yet, choose one to finish setting up the volume");
+        loop {
+            let password = Self::prompt("New root password: ")?;
+            if password.is_empty() {
+                continue;
+            }
+            if Self::prompt("Retype new root password: ")? == password {
+                return file_system.set_password("root", &password);
+            }
+            eprintln!("Error: {}", ShellError::PasswordMismatch);
+        }
+    }
+
     /// Runs the shell loop, processing user input commands.
     ///
     /// This method starts a loop that continuously prompts the user for input,
@@ -147,7 +183,7 @@ impl Shell {
     pub fn run(&mut self) -> Result<()> {
         let mut running = true;
         while running {
-            print!("filesystem> ");
+            print!("{}@filesystem> ", self.user_name());
             io::stdout().flush()?;
             let mut line = String::new();
             io::stdin().read_line(&mut line)?;
@@ -191,35 +227,281 @@ impl Shell {
     /// - `Err(e)`: If an error occurs during command execution.
     fn execute_command(&mut self, cmd: &str, args: &[&str]) -> Result<()> {
         command_handler! {self, cmd, args, {
-            "format" => format(0), // No arguments expected for format
+            "format" => format(0, 2), // Optionally expects -i followed by linear or hashed
             "create" => create_file_stdio(1), // Expects exactly 1 argument
             "cat" => read_file(1), // Expects exactly 1 argument
+            "hexdump" => hexdump(1), // Expects exactly 1 argument
+            "xxd" => xxd(1), // Expects exactly 1 argument
+            "base64" => base64(2), // Expects -d and the file to create from base64 read from stdin
             "ls" => list_dir(0), // No arguments expected for ls
             "cp" => copy_entry(2), // Expects exactly 2 arguments
-            "mv" => move_entry(2), // Expects exactly 2 arguments
-            "append" => append_file(2), // Expects exactly 2 arguments
+            "mv" => move_entry(2, 3), // Expects a source and a destination, optionally preceded by -f
+            "append" => append(1, 2), // Expects a source file and a destination, or only a destination to append stdin to
+            "truncate" => truncate(3), // Expects -s, the size and the file
             "mkdir" => create_dir(1), // Expects exactly 1 argument
             "cd" => change_dir(1), // Expects exactly 1 argument
             "pwd" => print_working_dir(0), // No arguments expected for pwd
-            "chmod" => change_permissions(2), // Expects exactly 2 arguments
+            "chmod" => chmod(2, 3), // Expects a path and a mode, optionally preceded by -R
             "rm" => remove_entry(1), // Expects exactly 1 argument
             "exec" => execute_py(1), // Expects exactly 1 argument
+            "df" => df(0), // No arguments expected for df
+            "du" => du(1, 2), // Expects a path, optionally preceded by -s
+            "ln" => ln(2, 3), // Expects the target and the link name, optionally preceded by -s
+            "readlink" => readlink(1), // Expects exactly 1 argument
+            "stat" => stat(1), // Expects exactly 1 argument
+            "atime" => atime(1), // Expects strict, relatime or noatime
+            "chown" => chown(2), // Expects uid[:gid] or :gid followed by a path
+            "getfacl" => getfacl(1), // Expects exactly 1 argument
+            "setfacl" => setfacl(2, 3), // Expects -m or -x with entries, or -b, followed by a path
+            "login" => login(1), // Expects a user name, the password is prompted for
+            "su" => su(0, 1), // Optionally expects a user name, root by default
+            "exit" => exit(0), // No arguments expected for exit
+            "whoami" => whoami(0), // No arguments expected for whoami
+            "useradd" => useradd(1), // Expects exactly 1 argument
+            "passwd" => passwd(0, 1), // Optionally expects a user name, the current user by default
         }}
     }
 
-    function_handler! {format}
     function_handler! {create_file_stdio, 0}
     function_handler! {read_file, 0}
+    function_handler! {hexdump, 0}
+    function_handler! {xxd, 0}
     function_handler! {list_dir}
     function_handler! {copy_entry, 0, 1}
-    function_handler! {move_entry, 0, 1}
-    function_handler! {append_file, 0, 1}
     function_handler! {create_dir, 0}
     function_handler! {change_dir, 0}
     function_handler! {print_working_dir}
-    function_handler! {change_permissions, 0, 1}
     function_handler! {remove_entry, 0}
     function_handler! {execute_py, 0}
+    function_handler! {df}
+    function_handler! {readlink, 0}
+    function_handler! {stat, 0}
+    function_handler! {getfacl, 0}
+
+    /// Handles `format [-i linear|hashed]`, selecting the directory index of the new volume.
+    fn format(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [] => self.file_system.format(),
+            ["-i", index] => {
+                let dir_index = index.parse::<DirIndex>()?;
+                self.file_system.format_with(FormatOptions { dir_index })
+            }
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `du [-s] path`, printing one row per directory unless `-s` is given.
+    fn du(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [path] => self.file_system.du(path, false),
+            ["-s", path] => self.file_system.du(path, true),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `append source dest`, appending a file, and `append dest`, appending lines read from stdin.
+    fn append(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [source, dest] => self.file_system.append_file(source, dest),
+            [dest] => self
+                .file_system
+                .append_input(dest, &mut StdinInput::new(StdIOHandler)),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `truncate -s size path`, the size in bytes, or relative to the current size with `+` or `-`.
+    fn truncate(&mut self, args: &[&str]) -> Result<()> {
+        let ["-s", size, path] = args else {
+            return Err(ShellError::InvalidUsage.into());
+        };
+        let parse = |n: &str| n.parse::<u64>().map_err(|_| ShellError::InvalidUsage);
+        let len = match (size.strip_prefix('+'), size.strip_prefix('-')) {
+            (Some(grow), _) => self
+                .file_system
+                .metadata(path)?
+                .size
+                .checked_add(parse(grow)?)
+                .ok_or(ShellError::InvalidUsage)?,
+            (_, Some(shrink)) => self
+                .file_system
+                .metadata(path)?
+                .size
+                .saturating_sub(parse(shrink)?),
+            _ => parse(size)?,
+        };
+        self.file_system.truncate(path, len)
+    }
+
+    /// Handles `base64 -d path`, creating a file from base64 lines read from stdin until an empty line.
+    fn base64(&mut self, args: &[&str]) -> Result<()> {
+        let ["-d", path] = args else {
+            return Err(ShellError::InvalidUsage.into());
+        };
+        let encoded = StdinInput::new(StdIOHandler).read_data()?;
+        self.file_system
+            .create_file_from_base64(path, &String::from_utf8_lossy(&encoded))
+    }
+
+    /// Handles `ln [-s] target link`, creating a hard link or, with `-s`, a symbolic link.
+    fn ln(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [target, link] => self.file_system.create_hard_link(target, link),
+            ["-s", target, link] => self.file_system.create_symlink(target, link),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `atime strict|relatime|noatime`, choosing when reads update access times.
+    fn atime(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [policy] => {
+                self.file_system
+                    .set_atime_policy(policy.parse::<AtimePolicy>()?);
+                Ok(())
+            }
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `mv [-f] source dest`, replacing an existing destination with `-f`.
+    fn move_entry(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [source, dest] => self.file_system.move_entry(source, dest),
+            ["-f", source, dest] => self.file_system.move_entry_overwrite(source, dest),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `chmod [-R] path mode`, the mode given as `755` or `u+x,g-w`.
+    fn chmod(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            [path, mode] => self.file_system.change_permissions(path, mode),
+            ["-R", path, mode] => self.file_system.change_permissions_recursive(path, mode),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Handles `chown uid[:gid] path` and `chown :gid path`.
+    fn chown(&mut self, args: &[&str]) -> Result<()> {
+        let [owner, path] = args else {
+            return Err(ShellError::InvalidUsage.into());
+        };
+        let (uid, gid) = match owner.split_once(':') {
+            Some((uid, gid)) => (uid, Some(gid)),
+            None => (*owner, None),
+        };
+        let parse = |id: &str| id.parse::<u16>().map_err(|_| ShellError::InvalidUsage);
+        let uid = if uid.is_empty() { None } else { Some(parse(uid)?) };
+        let gid = gid.map(parse).transpose()?;
+        if uid.is_none() && gid.is_none() {
+            return Err(ShellError::InvalidUsage.into());
+        }
+
+        self.file_system.change_owner(path, uid, gid)
+    }
+
+    /// Handles `setfacl -m entries path`, `setfacl -x entries path` and `setfacl -b path`,
+    /// the entries comma separated like `u:1000:rwx,d:g:100:r-x`.
+    fn setfacl(&mut self, args: &[&str]) -> Result<()> {
+        match args {
+            ["-m", specs, path] => self.file_system.modify_acl(path, specs),
+            ["-x", specs, path] => self.file_system.remove_acl_entries(path, specs),
+            ["-b", path] => self.file_system.set_acl(path, &Acl::default()),
+            _ => Err(ShellError::InvalidUsage.into()),
+        }
+    }
+
+    /// Returns the name of the current user, or its id if it has no account.
+    fn user_name(&self) -> String {
+        let credentials = self.file_system.credentials();
+        match self.file_system.current_user() {
+            Ok(Some(user)) => user.name,
+            _ if credentials.uid == NOBODY_UID => "nobody".to_string(),
+            _ => credentials.uid.to_string(),
+        }
+    }
+
+    /// Prints `label` and reads one line from stdin without its line break.
+    ///
+    /// Fails once stdin is closed, so callers that ask again don't loop forever.
+    fn prompt(label: &str) -> Result<String> {
+        print!("{}", label);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// Handles `login name`, starting a new session in the home directory of the user.
+    fn login(&mut self, args: &[&str]) -> Result<()> {
+        let password = Self::prompt("Password: ")?;
+        self.file_system.login(args[0], &password)?;
+        self.previous_credentials.clear();
+        Ok(())
+    }
+
+    /// Handles `su [name]`, acting as another user until `exit`.
+    ///
+    /// Only users other than root are asked for the password of the target.
+    fn su(&mut self, args: &[&str]) -> Result<()> {
+        let name = args.first().copied().unwrap_or("root");
+        let previous = self.file_system.credentials();
+        let password = if previous.is_root() {
+            None
+        } else {
+            Some(Self::prompt("Password: ")?)
+        };
+
+        self.file_system.switch_user(name, password.as_deref())?;
+        self.previous_credentials.push(previous);
+        Ok(())
+    }
+
+    /// Handles `exit`, returning to the user that was active before the last `su`.
+    fn exit(&mut self, _args: &[&str]) -> Result<()> {
+        let previous = self
+            .previous_credentials
+            .pop()
+            .ok_or(ShellError::InvalidUsage)?;
+        self.file_system.set_credentials(previous);
+        Ok(())
+    }
+
+    /// Handles `whoami`, printing the name of the current user.
+    fn whoami(&mut self, _args: &[&str]) -> Result<()> {
+        let name = self.user_name();
+        self.file_system.io_handler.write(name)
+    }
+
+    /// Handles `useradd name`, creating a locked account until `passwd` gives it a password.
+    fn useradd(&mut self, args: &[&str]) -> Result<()> {
+        let user = self.file_system.add_user(args[0])?;
+        self.file_system.io_handler.write(format!(
+            "Added {} with uid {}, set a password with passwd {}",
+            user.name, user.uid, user.name
+        ))
+    }
+
+    /// Handles `passwd [name]`, users other than root have to confirm their current password.
+    fn passwd(&mut self, args: &[&str]) -> Result<()> {
+        let name = match args.first() {
+            Some(name) => name.to_string(),
+            None => self.user_name(),
+        };
+        if !self.file_system.credentials().is_root() {
+            let current = Self::prompt("Current password: ")?;
+            self.file_system.authenticate(&name, &current)?;
+        }
+
+        let password = Self::prompt("New password: ")?;
+        if Self::prompt("Retype new password: ")? != password {
+            return Err(ShellError::PasswordMismatch.into());
+        }
+        self.file_system.set_password(&name, &password)
+    }
 
     /// Displays help information for available commands.
     ///
@@ -228,8 +510,10 @@ impl Shell {
     /// the shell supports.
     fn help() {
         let commands = [
-            "format", "create", "cat", "ls", "cp", "mv", "rm", "append", "mkdir", "cd", "pwd",
-            "chmod", "help", "quit",
+            "format", "create", "cat", "hexdump", "xxd", "base64", "ls", "cp", "mv", "rm",
+            "append", "truncate", "mkdir", "cd", "pwd", "chmod", "chown", "getfacl", "setfacl",
+            "df", "du", "ln", "readlink", "stat", "atime", "login", "su", "exit", "whoami",
+            "useradd", "passwd", "help", "quit",
         ];
 
         for command in commands {